@@ -0,0 +1,535 @@
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#ifndef SPIRAL_CITY_H
+#define SPIRAL_CITY_H
+
+/* Regenerate with: cbindgen --config cbindgen.toml --output include/spiral_city.h */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Turns between two contract offers.
+ */
+#define CONTRACT_INTERVAL 5
+
+/**
+ * Contracts that can be running at once.
+ */
+#define MAX_CONTRACTS 3
+
+#define SPIRAL_CITY_NO_BUILDING -1
+
+#define SPIRAL_CITY_RUBBLE -2
+
+#define SPIRAL_CITY_HOUSE 0
+
+#define SPIRAL_CITY_FOREST 1
+
+#define SPIRAL_CITY_QUARRY 2
+
+#define SPIRAL_CITY_WORKSHOP 3
+
+#define SPIRAL_CITY_FARM 4
+
+#define SPIRAL_CITY_FISHERY 5
+
+#define SPIRAL_CITY_WAREHOUSE 6
+
+#define SPIRAL_CITY_MARKET 7
+
+#define SPIRAL_CITY_COLOSSUS 8
+
+#define SPIRAL_CITY_GARDENS 9
+
+#define SPIRAL_CITY_NO_EVENT -1
+
+#define SPIRAL_CITY_FIRE 0
+
+#define SPIRAL_CITY_STORM 1
+
+#define SPIRAL_CITY_MINING_BOOM 2
+
+#define SPIRAL_CITY_SETTLERS 3
+
+#define SPIRAL_CITY_PLAINS 0
+
+#define SPIRAL_CITY_HILLS 1
+
+#define SPIRAL_CITY_RIVER 2
+
+#define SPIRAL_CITY_MOUNTAIN 3
+
+#define SPIRAL_CITY_FERTILE 4
+
+#define SPIRAL_CITY_NO_DISTRICT -1
+
+#define SPIRAL_CITY_WOODLAND 0
+
+#define SPIRAL_CITY_MINES 1
+
+#define SPIRAL_CITY_FARMLAND 2
+
+#define SPIRAL_CITY_HARBOUR 3
+
+#define SPIRAL_CITY_WOOD 0
+
+#define SPIRAL_CITY_ROCK 1
+
+#define SPIRAL_CITY_FOOD 2
+
+#define SPIRAL_CITY_NO_PERK -1
+
+#define SPIRAL_CITY_WORKSHOP_HOUSES 0
+
+#define SPIRAL_CITY_HEAD_START 1
+
+#define SPIRAL_CITY_WIDE_OFFERS 2
+
+#define SPIRAL_CITY_HARMONY 3
+
+#define SPIRAL_CITY_MASONRY 4
+
+#define SPIRAL_CITY_POLLUTION 0
+
+#define SPIRAL_CITY_HAPPINESS 1
+
+/**
+ * Distance within which the Hanging Gardens make houses happier.
+ */
+#define GARDENS_RADIUS 3
+
+/**
+ * Perks proposed in the draft.
+ */
+#define DRAFT_SIZE 3
+
+/**
+ * Storage available for each resource before any warehouse is built.
+ */
+#define BASE_STORAGE 20
+
+/**
+ * Wood paid to demolish a building.
+ */
+#define DEMOLITION_COST 1
+
+/**
+ * Skip tokens given to a game where the player steers the spiral.
+ */
+#define SKIP_TOKENS 3
+
+/**
+ * Turns between two tributes.
+ */
+#define TRIBUTE_INTERVAL 10
+
+/**
+ * Opaque handle on a game.
+ */
+typedef struct SpiralCityGame SpiralCityGame;
+
+typedef struct SpiralCityResources {
+  uint32_t total_people;
+  uint32_t occupied_people;
+  uint32_t housing;
+  uint32_t wood;
+  uint32_t rock;
+  uint32_t food;
+  uint32_t hungry_people;
+  uint32_t wood_cap;
+  uint32_t rock_cap;
+  uint32_t food_cap;
+} SpiralCityResources;
+
+typedef struct SpiralCityBounds {
+  int32_t x_min;
+  int32_t x_max;
+  int32_t y_min;
+  int32_t y_max;
+} SpiralCityBounds;
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+/**
+ * Create a new game. Release it with `spiral_city_free`.
+ */
+struct SpiralCityGame *spiral_city_new(uint64_t seed);
+
+/**
+ * Create a new game where the spiral can be steered at its corners and a
+ * few cells can be skipped. Release it with `spiral_city_free`.
+ */
+struct SpiralCityGame *spiral_city_new_steered(uint64_t seed);
+
+/**
+ * Create a new game where buildings go on any empty cell next to the city.
+ * Release it with `spiral_city_free`.
+ */
+struct SpiralCityGame *spiral_city_new_frontier(uint64_t seed);
+
+/**
+ * # Safety
+ * `game` must be null or a pointer returned by `spiral_city_new` that has
+ * not been freed yet.
+ */
+void spiral_city_free(struct SpiralCityGame *game);
+
+/**
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_turn(const struct SpiralCityGame *game);
+
+/**
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_is_over(const struct SpiralCityGame *game);
+
+/**
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uintptr_t spiral_city_offer_len(const struct SpiralCityGame *game);
+
+/**
+ * Building offered at `index`, or -1 if `index` is out of the offer.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_offer_get(const struct SpiralCityGame *game, uintptr_t index);
+
+/**
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_is_legal(const struct SpiralCityGame *game, uintptr_t index);
+
+/**
+ * Build the offered building at `index`. Returns false, leaving the game
+ * unchanged, if that choice is not legal.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_choose(struct SpiralCityGame *game, uintptr_t index);
+
+/**
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+struct SpiralCityResources spiral_city_resources(const struct SpiralCityGame *game);
+
+/**
+ * Bounds of the built part of the spiral, inclusive.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+struct SpiralCityBounds spiral_city_bounds(const struct SpiralCityGame *game);
+
+/**
+ * Building standing on cell (x, y), -2 for rubble or -1 if the cell is
+ * empty.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_cell(const struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Terrain of cell (x, y), built or not.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_terrain(const struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * District the building on cell (x, y) belongs to, or
+ * `SPIRAL_CITY_NO_DISTRICT`.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_district(const struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Demolish the building on cell (x, y), leaving rubble. Returns false,
+ * leaving the game unchanged, if that is not possible.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_demolish(struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Build the offered building at `index` on the rubble at (x, y). Returns
+ * false, leaving the game unchanged, if that choice is not legal.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_rebuild(struct SpiralCityGame *game, uintptr_t index, int32_t x, int32_t y);
+
+/**
+ * Upgrade the building on cell (x, y) to its next tier, which takes the
+ * turn. Returns false, leaving the game unchanged, if that is not possible.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_upgrade(struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Staff the given kind of building first. This does not end the turn.
+ * Returns false, leaving the game unchanged, if `building` is not a
+ * building code or does not need workers.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_prioritise(struct SpiralCityGame *game, int32_t building);
+
+/**
+ * Number of workers assigned to the building on cell (x, y) this turn.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_cell_workers(const struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Buy `amount` units of the good `get` at the market, paying with the good
+ * `give` (`SPIRAL_CITY_WOOD`, `SPIRAL_CITY_ROCK` or `SPIRAL_CITY_FOOD`).
+ * This does not end the turn. Returns false, leaving the game unchanged,
+ * if that trade is not possible.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_trade(struct SpiralCityGame *game, int32_t give, int32_t get, uint32_t amount);
+
+/**
+ * Build the offered building at `index` on the empty cell (x, y) next to
+ * the city, in a game created with `spiral_city_new_frontier`. Returns
+ * false, leaving the game unchanged, if that is not possible.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_build_at(struct SpiralCityGame *game, uintptr_t index, int32_t x, int32_t y);
+
+/**
+ * Make the spiral turn the other way at the coming corner. This does not
+ * end the turn. Returns false if the game is not steered or the next cell
+ * is not a corner.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_steer(struct SpiralCityGame *game);
+
+/**
+ * Leave the next cell empty for later, using a skip token. This does not
+ * end the turn. Returns false if no skip token is left.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_skip(struct SpiralCityGame *game);
+
+/**
+ * Skip tokens left.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_skip_tokens(const struct SpiralCityGame *game);
+
+/**
+ * Perk proposed at `index` of the draft, or `SPIRAL_CITY_NO_PERK` if
+ * `index` is out of the draft or the draft is over.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_perk_draft_get(const struct SpiralCityGame *game, uintptr_t index);
+
+/**
+ * Keep the drafted perk at `index` for the whole run. This does not end
+ * the turn. Returns false after the first turn or for a wrong index.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_pick_perk(struct SpiralCityGame *game, uintptr_t index);
+
+/**
+ * Whether `perk` applies to this run.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_has_perk(const struct SpiralCityGame *game, int32_t perk);
+
+/**
+ * Draw a new offer, using a reroll. This does not end the turn. Returns
+ * false if no reroll is left.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_reroll(struct SpiralCityGame *game);
+
+/**
+ * Rerolls left.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_rerolls(const struct SpiralCityGame *game);
+
+/**
+ * Turn at the end of which the next tribute is taken.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_tribute_due(const struct SpiralCityGame *game);
+
+/**
+ * Amount of `good` demanded by the next tribute, or -1 for an unknown good.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_tribute(const struct SpiralCityGame *game, int32_t good);
+
+/**
+ * Take on the contract offered. This does not end the turn. Returns false
+ * if no contract is offered or too many are running.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_accept_contract(struct SpiralCityGame *game);
+
+/**
+ * Contracts accepted and not settled yet.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uintptr_t spiral_city_contracts(const struct SpiralCityGame *game);
+
+/**
+ * Price of the next unit of `good` at the market, or -1 without a market
+ * or for an unknown good.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_trade_price(const struct SpiralCityGame *game, int32_t good);
+
+/**
+ * Event that happened after the last turn, `SPIRAL_CITY_NO_EVENT` if none.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_last_event(const struct SpiralCityGame *game);
+
+/**
+ * Value of a field (`SPIRAL_CITY_POLLUTION` or `SPIRAL_CITY_HAPPINESS`) on
+ * cell (x, y), or 0 for an unknown field.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_field(const struct SpiralCityGame *game, int32_t field, int32_t x, int32_t y);
+
+/**
+ * City-wide happiness, the average over the houses.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+int32_t spiral_city_happiness(const struct SpiralCityGame *game);
+
+/**
+ * Rings of the spiral completed so far.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_rings(const struct SpiralCityGame *game);
+
+/**
+ * Buildings that can still be placed for free, earned by completing rings.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_free_buildings(const struct SpiralCityGame *game);
+
+/**
+ * Tier of the building on cell (x, y), from 1, or 0 if the cell is empty.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+uint32_t spiral_city_cell_level(const struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Whether the building on cell (x, y) paid its upkeep this turn. Empty
+ * cells are reported as inactive.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`.
+ */
+bool spiral_city_cell_is_active(const struct SpiralCityGame *game, int32_t x, int32_t y);
+
+/**
+ * Write the position where the next building will be placed.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`, `x` and `y`
+ * must be valid for writes.
+ */
+void spiral_city_next_position(const struct SpiralCityGame *game, int32_t *x, int32_t *y);
+
+/**
+ * Serialise the state, offer and legal moves as a JSON string.
+ *
+ * # Safety
+ * `game` must be a live pointer returned by `spiral_city_new`. The result
+ * must be released with `spiral_city_string_free`.
+ */
+char *spiral_city_serialize(const struct SpiralCityGame *game);
+
+/**
+ * # Safety
+ * `string` must be null or a pointer returned by `spiral_city_serialize`
+ * that has not been freed yet.
+ */
+void spiral_city_string_free(char *string);
+
+#endif  /* SPIRAL_CITY_H */
@@ -1,23 +1,82 @@
-use enum_derived::Rand;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::resources::Resources;
+use crate::districts;
+use crate::fields;
+use crate::milestones::Modifier;
+use crate::perks::{self, Perk};
+use crate::resources::{DEFAULT_TRADE_RATES, Resources};
 use crate::states::State;
+use crate::terrain::Terrain;
 
-#[derive(Debug, Clone, Copy, Rand, PartialEq)]
+/// Highest tier a building can be upgraded to.
+pub const MAX_LEVEL: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Building {
     House,
     Forest,
     Quarry,
     Workshop,
+    Farm,
+    Fishery,
+    Warehouse,
+    Market,
+    Colossus,
+    Gardens,
 }
 
 impl Building {
+    pub const ALL: [Building; 10] = [
+        Building::House,
+        Building::Forest,
+        Building::Quarry,
+        Building::Workshop,
+        Building::Farm,
+        Building::Fishery,
+        Building::Warehouse,
+        Building::Market,
+        Building::Colossus,
+        Building::Gardens,
+    ];
+
+    /// Landmarks spanning several spiral cells, only offered late in the
+    /// game and never drawn by `random`.
+    pub const WONDERS: [Building; 2] = [Building::Colossus, Building::Gardens];
+
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::ALL[rng.gen_range(0..Self::ALL.len() - Self::WONDERS.len())]
+    }
+
+    pub fn is_wonder(&self) -> bool {
+        Self::WONDERS.contains(self)
+    }
+
+    /// Consecutive spiral cells the building takes.
+    pub fn size(&self) -> usize {
+        match *self {
+            Building::Colossus => 2,
+            Building::Gardens => 3,
+            _ => 1,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|b| b == self).unwrap()
+    }
+
     pub fn convert_to_char(&self) -> char {
         match *self {
             Building::House => '🏠',
             Building::Forest => '🌲',
             Building::Quarry => '🪨',
             Building::Workshop => '🪚',
+            Building::Farm => '🌾',
+            Building::Fishery => '🎣',
+            Building::Warehouse => '📦',
+            Building::Market => '🏪',
+            Building::Colossus => '🗿',
+            Building::Gardens => '🪴',
         }
     }
 
@@ -27,6 +86,12 @@ impl Building {
             Building::Forest => "Forest".to_string(),
             Building::Quarry => "Quarry".to_string(),
             Building::Workshop => "Workshop".to_string(),
+            Building::Farm => "Farm".to_string(),
+            Building::Fishery => "Fishery".to_string(),
+            Building::Warehouse => "Warehouse".to_string(),
+            Building::Market => "Market".to_string(),
+            Building::Colossus => "Colossus".to_string(),
+            Building::Gardens => "Gardens".to_string(),
         }
     }
 
@@ -41,101 +106,338 @@ impl Building {
     pub fn cost(&self) -> Vec<Resources> {
         match *self {
             Building::House => vec![Resources::Wood(1)],
-            Building::Forest => vec![Resources::WorkingPeople(1)],
-            Building::Quarry => vec![Resources::WorkingPeople(1)],
-            Building::Workshop => vec![
-                Resources::WorkingPeople(2),
-                Resources::Wood(1),
-                Resources::Rock(1),
-            ],
+            Building::Forest => vec![],
+            Building::Quarry => vec![],
+            Building::Workshop => vec![Resources::Wood(1), Resources::Rock(1)],
+            Building::Farm => vec![],
+            Building::Fishery => vec![Resources::Wood(1)],
+            Building::Warehouse => vec![Resources::Wood(2), Resources::Rock(1)],
+            Building::Market => vec![Resources::Wood(2), Resources::Rock(2)],
+            Building::Colossus => vec![Resources::Wood(8), Resources::Rock(12)],
+            Building::Gardens => vec![Resources::Wood(10), Resources::Rock(6), Resources::Food(10)],
+        }
+    }
+
+    /// Room for people given by a house of this level.
+    pub fn housing(&self, level: u32) -> u32 {
+        match *self {
+            Building::House => 2 * level,
+            _ => 0,
+        }
+    }
+
+    /// Workers needed to run at full capacity. Workers are assigned again
+    /// every turn, an understaffed building produces proportionally less.
+    pub fn workers_needed(&self) -> u32 {
+        match *self {
+            Building::Forest | Building::Quarry | Building::Farm | Building::Fishery => 1,
+            Building::Workshop => 2,
+            Building::House
+            | Building::Warehouse
+            | Building::Market
+            | Building::Colossus
+            | Building::Gardens => 0,
+        }
+    }
+
+    /// Building whose name (e.g. "quarry") is given, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|building| building.get_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Resources consumed every turn. A building whose upkeep cannot be paid
+    /// is inactive for the turn: it produces nothing and has no effect on its
+    /// neighbours.
+    pub fn upkeep(&self) -> Vec<Resources> {
+        match *self {
+            Building::Workshop => vec![Resources::Wood(1)],
+            Building::Fishery => vec![Resources::Wood(1)],
+            _ => vec![],
         }
     }
 
-    pub fn characteristics_to_string(&self) -> String {
+    pub fn tier_name(&self, level: u32) -> String {
+        let names = match *self {
+            Building::House => ["House", "Cottage", "Manor"],
+            Building::Forest => ["Forest", "Managed Forest", "Sawmill"],
+            Building::Quarry => ["Quarry", "Deep Quarry", "Mine"],
+            Building::Workshop => ["Workshop", "Factory", "Foundry"],
+            Building::Farm => ["Farm", "Orchard", "Plantation"],
+            Building::Fishery => ["Fishery", "Harbour", "Port"],
+            Building::Warehouse => ["Warehouse", "Depot", "Storehouse"],
+            Building::Market => ["Market", "Bazaar", "Exchange"],
+            Building::Colossus => ["Colossus"; 3],
+            Building::Gardens => ["Hanging Gardens"; 3],
+        };
+        names[(level.clamp(1, MAX_LEVEL) - 1) as usize].to_string()
+    }
+
+    pub fn tier_char(&self, level: u32) -> char {
+        let chars = match *self {
+            Building::House => ['🏠', '🏡', '🏰'],
+            Building::Forest => ['🌲', '🌳', '🪓'],
+            Building::Quarry => ['🪨', '🗻', '💎'],
+            Building::Workshop => ['🪚', '🔨', '🏭'],
+            Building::Farm => ['🌾', '🌽', '🚜'],
+            Building::Fishery => ['🎣', '🐟', '🚢'],
+            Building::Warehouse => ['📦', '🚚', '🏬'],
+            Building::Market => ['🏪', '🛒', '🏦'],
+            Building::Colossus => ['🗿'; 3],
+            Building::Gardens => ['🪴'; 3],
+        };
+        chars[(level.clamp(1, MAX_LEVEL) - 1) as usize]
+    }
+
+    pub fn tier_to_string(&self, level: u32) -> String {
+        format!("{} {}", self.tier_char(level), self.tier_name(level))
+    }
+
+    /// Cost to upgrade this building from `level` to the next tier.
+    pub fn upgrade_cost(&self, level: u32) -> Vec<Resources> {
+        let n = 2 * level as i32;
+        vec![Resources::Wood(n), Resources::Rock(n)]
+    }
+
+    pub fn characteristics_to_string(&self, level: u32) -> String {
         let mut res = String::new();
         match *self {
             Building::House => {
                 res.push_str("    Cost           : -1 wood 🪵\n");
-                res.push_str("    Production     : +1 people 👥 (once when built)\n");
-                res.push_str("    Special effect : none\n");
+                res.push_str(&format!(
+                    "    Production     : room for {} people 👥, who move in over time\n",
+                    2 * level
+                ));
+                res.push_str("    Upkeep         : each person eats 1 food 🍞 / turn\n");
+                res.push_str("    Special effect : people move in faster next to a forest 🌲\n");
             }
             Building::Forest => {
-                res.push_str("    Cost           : 1 working people 👥\n");
-                res.push_str("    Production     : +2 wood 🪵 / turn\n");
+                res.push_str("    Cost           : none\n");
+                res.push_str("    Workers        : 1 👥\n");
+                res.push_str(&format!(
+                    "    Production     : +{} wood 🪵 / turn\n",
+                    level + 1
+                ));
                 res.push_str("    Special effect : -1 wood 🪵 if next to a quarry 🪨\n");
             }
             Building::Quarry => {
-                res.push_str("    Cost           : 1 working people 👥\n");
-                res.push_str("    Production     : +rocks 🪨 / turn\n");
+                res.push_str("    Cost           : none\n");
+                res.push_str("    Workers        : 1 👥\n");
+                res.push_str(&format!(
+                    "    Production     : +{} rocks 🪨 / turn\n",
+                    level + 1
+                ));
                 res.push_str("    Special effect : -1 rock 🪨 if next to a forest 🌲\n");
             }
             Building::Workshop => {
+                res.push_str("    Cost           : -1 wood 🪵 | -1 rock 🪨\n");
+                res.push_str("    Workers        : 2 👥 (half the boost, rounded up, with 1)\n");
+                res.push_str("    Production     : none\n");
+                res.push_str("    Upkeep         : -1 wood 🪵 / turn (inactive 💤 if unpaid)\n");
+                res.push_str(&format!(
+                    "    Special effect : adjacent buildings produce +{level} resource\n"
+                ));
+            }
+            Building::Farm => {
+                res.push_str("    Cost           : none\n");
+                res.push_str("    Workers        : 1 👥\n");
+                res.push_str(&format!("    Production     : +{level} food 🍞 / turn\n"));
+                res.push_str("    Special effect : +1 food 🍞 per adjacent house 🏠\n");
+            }
+            Building::Fishery => {
+                res.push_str("    Cost           : -1 wood 🪵\n");
+                res.push_str("    Workers        : 1 👥\n");
+                res.push_str(&format!(
+                    "    Production     : +{} food 🍞 / turn\n",
+                    level + 1
+                ));
+                res.push_str("    Upkeep         : -1 wood 🪵 / turn (inactive 💤 if unpaid)\n");
+                res.push_str("    Special effect : -1 food 🍞 if next to a quarry 🪨\n");
+            }
+            Building::Warehouse => {
+                res.push_str("    Cost           : -2 wood 🪵 | -1 rock 🪨\n");
+                res.push_str(&format!(
+                    "    Production     : +{} storage for wood 🪵, rock 🪨 and food 🍞\n",
+                    10 * level
+                ));
                 res.push_str(
-                    "    Cost           : 2 working people 👥 | -1 wood 🪵 | -1 rock 🪨\n",
+                    "    Special effect : +5 storage per adjacent forest 🌲, quarry 🪨, farm 🌾 or fishery 🎣 for what it produces\n",
                 );
+            }
+            Building::Market => {
+                res.push_str("    Cost           : -2 wood 🪵 | -2 rock 🪨\n");
                 res.push_str("    Production     : none\n");
-                res.push_str("    Special effect : adjacent buildings produce +1 resource\n");
+                res.push_str(&format!(
+                    "    Special effect : trade wood 🪵, rock 🪨 and food 🍞, {} for 1, each purchase raises the price\n",
+                    DEFAULT_TRADE_RATES.base_price(level)
+                ));
+            }
+            Building::Colossus => {
+                res.push_str("    Cost           : -8 wood 🪵 | -12 rock 🪨\n");
+                res.push_str("    Size           : 2 spiral cells\n");
+                res.push_str("    Special effect : every quarry 🪨 produces +1 rock 🪨\n");
+            }
+            Building::Gardens => {
+                res.push_str("    Cost           : -10 wood 🪵 | -6 rock 🪨 | -10 food 🍞\n");
+                res.push_str("    Size           : 3 spiral cells\n");
+                res.push_str(&format!(
+                    "    Special effect : +2 happiness 😊 for houses 🏠 within {} cells\n",
+                    fields::GARDENS_RADIUS
+                ));
             }
         }
+        if level < MAX_LEVEL && !self.is_wonder() {
+            res.push_str(&format!(
+                "    Upgrade        : {} → {}\n",
+                Resources::cost_to_string(&self.upgrade_cost(level)),
+                self.tier_to_string(level + 1)
+            ));
+        }
         res
     }
 
+    /// Whether this building can be paid for and placed on the next cell.
     pub fn can_be_built(&self, state: &State) -> bool {
-        let cost = self.cost();
-        for resource in cost {
-            match resource {
-                Resources::WorkingPeople(n) => {
-                    if state.owned_resources.total_people - state.owned_resources.occupied_people
-                        < n as u32
-                    {
-                        return false;
-                    }
-                }
-                Resources::Wood(n) => {
-                    if state.owned_resources.wood < n as u32 {
-                        return false;
-                    }
-                }
-                Resources::Rock(n) => {
-                    if state.owned_resources.rock < n as u32 {
-                        return false;
-                    }
-                }
+        (state.is_free(*self) || state.owned_resources.can_afford(&perks::cost(state, *self)))
+            && state.target_for(*self).is_some()
+    }
+
+    /// Extra storage capacity given by this building, one entry per resource.
+    pub fn storage(&self, coordinates: (i32, i32), state: &State) -> Vec<Resources> {
+        if *self != Building::Warehouse {
+            return vec![];
+        }
+        let base = 10 * state.level(coordinates) as i32;
+        let (mut wood, mut rock, mut food) = (base, base, base);
+        let (x, y) = coordinates;
+        for neighbour in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            match state.spiral.get(&neighbour) {
+                Some(Building::Forest) => wood += 5,
+                Some(Building::Quarry) => rock += 5,
+                Some(Building::Farm) | Some(Building::Fishery) => food += 5,
+                _ => {}
             }
         }
-        true
+        vec![
+            Resources::Wood(wood),
+            Resources::Rock(rock),
+            Resources::Food(food),
+        ]
     }
 
+    /// What the building produces this turn, boosted when it belongs to a
+    /// district.
     pub fn production(&self, coordinates: (i32, i32), state: &State) -> Option<Resources> {
+        self.base_production(coordinates, state)
+            .map(|produced| districts::boost(state, coordinates, produced))
+    }
+
+    fn base_production(&self, coordinates: (i32, i32), state: &State) -> Option<Resources> {
+        // Neighbours with their level, a workshop boosts by its level when
+        // fully staffed and by half of it, rounded up, with one worker
         let mut neighbours = vec![];
         let (x, y) = coordinates;
         for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
-            if state.spiral.contains_key(&(nx, ny)) {
-                neighbours.push(*state.spiral.get(&(nx, ny)).unwrap());
+            if let Some(&neighbour) = state.spiral.get(&(nx, ny))
+                && !state.inactive.contains(&(nx, ny))
+            {
+                let level = state.level((nx, ny));
+                let strength = match neighbour.workers_needed() {
+                    0 => level,
+                    needed => (level * state.workers_on((nx, ny))).div_ceil(needed),
+                } as i32;
+                neighbours.push((neighbour, strength));
             }
         }
+        // Each upgrade adds one to the base production, and so does a happy
+        // city
+        let bonus = state.level(coordinates) as i32 - 1 + state.happiness_bonus();
+        let terrain = state.terrain(coordinates);
+        let near_river = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .iter()
+            .any(|&neighbour| state.terrain(neighbour) == Terrain::River);
+        // Forests and quarries slow each other down, unless a perk says not
+        let harmony = state.perks.contains(&Perk::Harmony);
+        // Understaffed buildings produce proportionally less
+        let staffed = |amount: i32| {
+            amount.max(0) * state.workers_on(coordinates) as i32
+                / self.workers_needed().max(1) as i32
+        };
         match *self {
             Building::Forest => {
-                let mut nb_wood = 2;
-                for neighbour in neighbours {
+                let mut nb_wood = 2 + bonus;
+                if near_river {
+                    nb_wood += 1;
+                }
+                for (neighbour, level) in neighbours {
                     match neighbour {
-                        Building::Quarry => nb_wood -= 1,
-                        Building::Workshop => nb_wood += 1,
+                        Building::Quarry if !harmony => nb_wood -= 1,
+                        Building::Workshop => nb_wood += level,
                         _ => {}
                     }
                 }
-                Some(Resources::Wood(nb_wood))
+                // A storm halves wood production
+                if state.storm_turns > 0 {
+                    return Some(Resources::Wood(staffed(nb_wood) / 2));
+                }
+                Some(Resources::Wood(staffed(nb_wood)))
             }
             Building::Quarry => {
-                let mut nb_rock = 2;
-                for neighbour in neighbours {
+                let mut nb_rock = 2 + bonus;
+                if state.boom_turns > 0 {
+                    nb_rock += 1;
+                }
+                if state.has_wonder(Building::Colossus) {
+                    nb_rock += 1;
+                }
+                match terrain {
+                    Terrain::Hills => nb_rock += 1,
+                    Terrain::Mountain => nb_rock += 2,
+                    _ => {}
+                }
+                for (neighbour, level) in neighbours {
+                    match neighbour {
+                        Building::Forest if !harmony => nb_rock -= 1,
+                        Building::Workshop => nb_rock += level,
+                        _ => {}
+                    }
+                }
+                Some(Resources::Rock(staffed(nb_rock)))
+            }
+            Building::Farm => {
+                let mut nb_food = 1 + bonus;
+                if terrain == Terrain::Fertile {
+                    nb_food += 1;
+                }
+                if state.modifiers.contains(&Modifier::Harvest) {
+                    nb_food += 1;
+                }
+                for (neighbour, level) in neighbours {
+                    match neighbour {
+                        Building::House => nb_food += 1,
+                        Building::Workshop => nb_food += level,
+                        _ => {}
+                    }
+                }
+                Some(Resources::Food(staffed(nb_food)))
+            }
+            Building::Fishery => {
+                let mut nb_food = 2 + bonus;
+                if terrain == Terrain::River {
+                    nb_food += 1;
+                }
+                if state.modifiers.contains(&Modifier::Harvest) {
+                    nb_food += 1;
+                }
+                for (neighbour, level) in neighbours {
                     match neighbour {
-                        Building::Forest => nb_rock -= 1,
-                        Building::Workshop => nb_rock += 1,
+                        Building::Quarry => nb_food -= 1,
+                        Building::Workshop => nb_food += level,
                         _ => {}
                     }
                 }
-                Some(Resources::Rock(nb_rock))
+                Some(Resources::Food(staffed(nb_food)))
             }
             _ => None,
         }
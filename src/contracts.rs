@@ -0,0 +1,173 @@
+//! Contracts offered to the city every few turns: a target to reach before
+//! a deadline, paid with a reward when met and punished with a penalty when
+//! the deadline passes.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+
+use crate::buildings::Building;
+use crate::resources::{Good, Resources};
+use crate::states::State;
+
+/// Turns between two contract offers.
+pub const CONTRACT_INTERVAL: u32 = 5;
+/// Turns given to fulfil a contract once offered.
+const CONTRACT_DURATION: u32 = 10;
+/// Contracts that can be running at once.
+pub const MAX_CONTRACTS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Goal {
+    /// Have `amount` of `good` in storage.
+    Bank { good: Good, amount: u32 },
+    /// Have `count` forests next to at least one workshop.
+    ForestsByWorkshops { count: u32 },
+    /// Complete `rings` rings of the spiral.
+    Rings { rings: u32 },
+}
+
+impl Goal {
+    /// How far the city is towards the goal, and the target.
+    pub fn progress(&self, state: &State) -> (u32, u32) {
+        match *self {
+            Goal::Bank { good, amount } => (state.owned_resources.amount(good), amount),
+            Goal::ForestsByWorkshops { count } => (forests_by_workshops(state), count),
+            Goal::Rings { rings } => (state.rings, rings),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Goal::Bank { good, amount } => format!("bank {amount} {}", good.symbol()),
+            Goal::ForestsByWorkshops { count } => {
+                format!("have {count} forests 🌲 next to workshops 🪚")
+            }
+            Goal::Rings { rings } => format!("complete {rings} rings 🌀"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contract {
+    pub goal: Goal,
+    /// Last turn at which the goal can be met.
+    pub deadline: u32,
+    pub reward: Vec<Resources>,
+    pub penalty: Vec<Resources>,
+}
+
+impl Contract {
+    /// Draw a contract a bit beyond what the city has now. Only goods below
+    /// their storage cap can be asked for, so that the goal is never met
+    /// from the start.
+    pub fn roll<R: Rng>(state: &State, rng: &mut R) -> Self {
+        let resources = &state.owned_resources;
+        let bankable: Vec<Good> = Good::ALL
+            .into_iter()
+            .filter(|&good| resources.amount(good) < resources.cap(good))
+            .collect();
+        let first = if bankable.is_empty() { 1 } else { 0 };
+        let goal = match rng.gen_range(first..3) {
+            0 => {
+                let good = *bankable.choose(rng).unwrap();
+                let amount = resources.amount(good) + 10;
+                Goal::Bank {
+                    good,
+                    amount: amount.min(resources.cap(good)),
+                }
+            }
+            1 => Goal::ForestsByWorkshops {
+                count: forests_by_workshops(state) + 2,
+            },
+            _ => Goal::Rings {
+                rings: state.rings + 1,
+            },
+        };
+        Contract {
+            goal,
+            deadline: state.turn + CONTRACT_DURATION,
+            reward: vec![Resources::Wood(5), Resources::Rock(5), Resources::Food(5)],
+            penalty: vec![Resources::Wood(3), Resources::Rock(3), Resources::Food(3)],
+        }
+    }
+
+    pub fn is_met(&self, state: &State) -> bool {
+        let (current, target) = self.goal.progress(state);
+        current >= target
+    }
+
+    pub fn describe(&self, state: &State) -> String {
+        let (current, target) = self.goal.progress(state);
+        format!(
+            "{} ({current}/{target}) by turn {}: +{} or -{}",
+            self.goal.describe(),
+            self.deadline,
+            Resources::cost_to_string(&self.reward),
+            Resources::cost_to_string(&self.penalty)
+        )
+    }
+}
+
+/// Forests with at least one active workshop next to them.
+fn forests_by_workshops(state: &State) -> u32 {
+    state
+        .spiral
+        .iter()
+        .filter(|&(&(x, y), &building)| {
+            building == Building::Forest
+                && [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .iter()
+                    .any(|neighbour| {
+                        state.spiral.get(neighbour) == Some(&Building::Workshop)
+                            && !state.inactive.contains(neighbour)
+                    })
+        })
+        .count() as u32
+}
+
+#[test]
+fn test_contracts() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let mut state = State::initialize();
+    state.spiral.insert((1, 0), Building::Forest);
+    state.spiral.insert((2, 0), Building::Workshop);
+    state.spiral.insert((0, 1), Building::Forest);
+    assert_eq!(forests_by_workshops(&state), 1);
+    state.inactive.insert((2, 0));
+    assert_eq!(forests_by_workshops(&state), 0);
+    state.inactive.clear();
+
+    let contract = Contract {
+        goal: Goal::Bank {
+            good: Good::Rock,
+            amount: 5,
+        },
+        deadline: 3,
+        reward: vec![Resources::Food(5)],
+        penalty: vec![Resources::Food(3)],
+    };
+    assert!(!contract.is_met(&state));
+    state.owned_resources.rock = 5;
+    assert!(contract.is_met(&state));
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..20 {
+        let contract = Contract::roll(&state, &mut rng);
+        assert!(!contract.is_met(&state));
+        assert_eq!(contract.deadline, CONTRACT_DURATION);
+    }
+
+    // Nothing left to bank once every good is at its cap
+    state.owned_resources.wood = state.owned_resources.wood_cap;
+    state.owned_resources.rock = state.owned_resources.rock_cap;
+    state.owned_resources.food = state.owned_resources.food_cap;
+    for _ in 0..20 {
+        let contract = Contract::roll(&state, &mut rng);
+        assert!(!matches!(contract.goal, Goal::Bank { .. }));
+        assert!(!contract.is_met(&state));
+    }
+}
@@ -0,0 +1,181 @@
+//! Connected groups of the same producer. Large enough groups become
+//! districts and produce more, which rewards planning the layout of the
+//! spiral ahead.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::buildings::Building;
+use crate::resources::Resources;
+use crate::states::State;
+
+/// Buildings needed in a cluster for each step of district bonus.
+pub const DISTRICT_SIZE: usize = 4;
+/// Production bonus, in percent, for each step of `DISTRICT_SIZE` buildings.
+const DISTRICT_BONUS: i32 = 50;
+const MAX_DISTRICT_BONUS: i32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum District {
+    Woodland,
+    Mines,
+    Farmland,
+    Harbour,
+}
+
+impl District {
+    pub const ALL: [District; 4] = [
+        District::Woodland,
+        District::Mines,
+        District::Farmland,
+        District::Harbour,
+    ];
+
+    /// District formed by a cluster of `size` buildings of this kind, if any.
+    pub fn of(building: Building, size: usize) -> Option<Self> {
+        if size < DISTRICT_SIZE {
+            return None;
+        }
+        match building {
+            Building::Forest => Some(District::Woodland),
+            Building::Quarry => Some(District::Mines),
+            Building::Farm => Some(District::Farmland),
+            Building::Fishery => Some(District::Harbour),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|d| d == self).unwrap()
+    }
+
+    pub fn convert_to_char(&self) -> char {
+        match *self {
+            District::Woodland => '🦌',
+            District::Mines => '🪙',
+            District::Farmland => '🐄',
+            District::Harbour => '⚓',
+        }
+    }
+}
+
+/// Cells connected to `coordinates` through active buildings of the same
+/// kind, sorted. Empty if there is no active building there.
+pub fn cluster(state: &State, coordinates: (i32, i32)) -> Vec<(i32, i32)> {
+    let Some(&kind) = state.spiral.get(&coordinates) else {
+        return vec![];
+    };
+    if state.inactive.contains(&coordinates) {
+        return vec![];
+    }
+    let mut visited = HashSet::from([coordinates]);
+    let mut stack = vec![coordinates];
+    while let Some((x, y)) = stack.pop() {
+        for neighbour in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if state.spiral.get(&neighbour) == Some(&kind)
+                && !state.inactive.contains(&neighbour)
+                && visited.insert(neighbour)
+            {
+                stack.push(neighbour);
+            }
+        }
+    }
+    let mut cells: Vec<(i32, i32)> = visited.into_iter().collect();
+    cells.sort();
+    cells
+}
+
+/// Every cluster of the city forming a district, with its cells.
+pub fn districts(state: &State) -> Vec<(District, Vec<(i32, i32)>)> {
+    let mut coordinates_list: Vec<(i32, i32)> = state.spiral.keys().copied().collect();
+    coordinates_list.sort();
+    let mut seen = HashSet::new();
+    let mut districts = vec![];
+    for coordinates in coordinates_list {
+        if seen.contains(&coordinates) {
+            continue;
+        }
+        let cells = cluster(state, coordinates);
+        seen.extend(cells.iter().copied());
+        if let Some(district) = District::of(state.spiral[&coordinates], cells.len()) {
+            districts.push((district, cells));
+        }
+    }
+    districts
+}
+
+pub fn district_at(state: &State, coordinates: (i32, i32)) -> Option<District> {
+    let building = *state.spiral.get(&coordinates)?;
+    District::of(building, cluster(state, coordinates).len())
+}
+
+/// Production bonus, in percent, of a district of `size` buildings: +50%
+/// from 4 buildings, +100% from 8.
+pub fn bonus(size: usize) -> i32 {
+    (DISTRICT_BONUS * (size / DISTRICT_SIZE) as i32).min(MAX_DISTRICT_BONUS)
+}
+
+/// `produced` by the building at `coordinates`, raised if it belongs to a
+/// district.
+pub fn boost(state: &State, coordinates: (i32, i32), produced: Resources) -> Resources {
+    let Some(&building) = state.spiral.get(&coordinates) else {
+        return produced;
+    };
+    let size = cluster(state, coordinates).len();
+    if District::of(building, size).is_none() {
+        return produced;
+    }
+    let percent = 100 + bonus(size);
+    match produced {
+        Resources::Wood(n) => Resources::Wood(n * percent / 100),
+        Resources::Rock(n) => Resources::Rock(n * percent / 100),
+        Resources::Food(n) => Resources::Food(n * percent / 100),
+        other => other,
+    }
+}
+
+#[test]
+fn test_districts() {
+    use crate::buildings::MAX_LEVEL;
+
+    let mut state = State::initialize();
+    for x in 1..=3 {
+        state.spiral.insert((x, 0), Building::Forest);
+    }
+    state.spiral.insert((1, 1), Building::Quarry);
+    assert_eq!(cluster(&state, (2, 0)).len(), 3);
+    assert_eq!(district_at(&state, (2, 0)), None);
+    assert!(districts(&state).is_empty());
+
+    state.spiral.insert((3, 1), Building::Forest);
+    assert_eq!(district_at(&state, (1, 0)), Some(District::Woodland));
+    assert_eq!(
+        districts(&state),
+        vec![(District::Woodland, vec![(1, 0), (2, 0), (3, 0), (3, 1)])]
+    );
+    assert_eq!(
+        boost(&state, (2, 0), Resources::Wood(3)),
+        Resources::Wood(4)
+    );
+    assert_eq!(
+        boost(&state, (1, 1), Resources::Rock(3)),
+        Resources::Rock(3)
+    );
+    assert_eq!(bonus(9), 100);
+
+    // An inactive building breaks the cluster
+    state.inactive.insert((3, 0));
+    assert_eq!(cluster(&state, (1, 0)), vec![(1, 0), (2, 0)]);
+    assert!(cluster(&state, (3, 0)).is_empty());
+    assert!(districts(&state).is_empty());
+
+    // The district map must not be mistaken for the normal one
+    for district in District::ALL {
+        for building in Building::ALL {
+            for level in 1..=MAX_LEVEL {
+                assert_ne!(district.convert_to_char(), building.tier_char(level));
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::buildings::Building;
+use crate::events::Event;
+use crate::game::Game;
+use crate::perks::MAX_OFFER_SIZE;
+use crate::resources::Resources;
+use crate::terrain::Terrain;
+
+/// Number of cells around the next spiral position encoded on each side.
+pub const OBSERVATION_RADIUS: i32 = 3;
+/// Size of the action space, the largest possible offer.
+pub const ACTION_SPACE: usize = MAX_OFFER_SIZE;
+const OBSERVATION_WIDTH: usize = (2 * OBSERVATION_RADIUS + 1) as usize;
+const RESOURCES_SIZE: usize = 10;
+/// Values per offer slot: one per building type, then one set when the slot
+/// is padding.
+const SLOT_SIZE: usize = Building::ALL.len() + 1;
+
+/// Flat numeric encoding of a game, always `Observation::SIZE` long:
+/// - one channel per building type over the square window centred on the
+///   next spiral position (1.0 where that building stands),
+/// - one channel per terrain type over the same window,
+/// - the resources (total people, occupied people, housing, wood, rock, food,
+///   hungry people, wood storage, rock storage, food storage),
+/// - one one-hot slot per building of the largest possible offer, the slots
+///   past the current offer marked as padding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub data: Vec<f32>,
+}
+
+impl Observation {
+    pub const SIZE: usize =
+        (Building::ALL.len() + Terrain::ALL.len()) * OBSERVATION_WIDTH * OBSERVATION_WIDTH
+            + RESOURCES_SIZE
+            + MAX_OFFER_SIZE * SLOT_SIZE;
+
+    fn from_game(game: &Game) -> Self {
+        let state = &game.state;
+        let mut data = vec![0.0; Self::SIZE];
+
+        let (cx, cy) = state.get_next_position();
+        let channel_size = OBSERVATION_WIDTH * OBSERVATION_WIDTH;
+        for (row, dy) in (-OBSERVATION_RADIUS..=OBSERVATION_RADIUS).enumerate() {
+            for (col, dx) in (-OBSERVATION_RADIUS..=OBSERVATION_RADIUS).enumerate() {
+                if let Some(building) = state.spiral.get(&(cx + dx, cy + dy)) {
+                    data[building.index() * channel_size + row * OBSERVATION_WIDTH + col] = 1.0;
+                }
+                let terrain = state.terrain((cx + dx, cy + dy));
+                let channel = Building::ALL.len() + terrain.index();
+                data[channel * channel_size + row * OBSERVATION_WIDTH + col] = 1.0;
+            }
+        }
+
+        let mut offset = (Building::ALL.len() + Terrain::ALL.len()) * channel_size;
+        let resources = &state.owned_resources;
+        for value in [
+            resources.total_people,
+            resources.occupied_people,
+            resources.housing,
+            resources.wood,
+            resources.rock,
+            resources.food,
+            resources.hungry_people,
+            resources.wood_cap,
+            resources.rock_cap,
+            resources.food_cap,
+        ] {
+            data[offset] = value as f32;
+            offset += 1;
+        }
+
+        for slot in 0..MAX_OFFER_SIZE {
+            let value = match game.offer.get(slot) {
+                Some(building) => building.index(),
+                None => Building::ALL.len(),
+            };
+            data[offset + slot * SLOT_SIZE + value] = 1.0;
+        }
+
+        Self { data }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StepInfo {
+    pub turn: u32,
+    pub built: Option<Building>,
+    pub delta_production: Vec<Resources>,
+    pub lost_production: Vec<Resources>,
+    pub event: Option<Event>,
+    pub legal_actions: Vec<bool>,
+}
+
+/// Gym-style wrapper around `Game`.
+///
+/// Actions are indices in the current offer. The action mask is always
+/// `ACTION_SPACE` long, whatever the size of the offer, and the padding
+/// slots are illegal. A step that builds something earns a reward of 1.0;
+/// an illegal action is penalised with -1.0 and leaves the game unchanged.
+/// The episode is done once no offered building can be built, which is
+/// also the case after a tribute could not be paid.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    game: Game,
+}
+
+impl Environment {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            game: Game::new(seed),
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(seed);
+        self.observation()
+    }
+
+    pub fn step(&mut self, action: usize) -> (Observation, f32, bool, StepInfo) {
+        let (reward, built, event) = match self.game.choose(action) {
+            Ok(building) => (
+                1.0,
+                Some(building),
+                self.game.history.last().unwrap().event.clone(),
+            ),
+            Err(_) => (-1.0, None, None),
+        };
+        let info = StepInfo {
+            turn: self.game.state.turn,
+            built,
+            delta_production: self.game.state.delta_production.clone(),
+            lost_production: self.game.state.lost_production.clone(),
+            event,
+            legal_actions: self.action_mask(),
+        };
+        let done = info.legal_actions.iter().all(|legal| !legal);
+        (self.observation(), reward, done, info)
+    }
+
+    pub fn observation(&self) -> Observation {
+        Observation::from_game(&self.game)
+    }
+
+    pub fn action_mask(&self) -> Vec<bool> {
+        let mut mask = self.game.legal_moves();
+        mask.resize(ACTION_SPACE, false);
+        mask
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+}
+
+#[test]
+fn test_environment_is_deterministic() {
+    let mut env1 = Environment::new(0);
+    let mut env2 = Environment::new(0);
+    assert_eq!(env1.reset(42), env2.reset(42));
+    assert_eq!(env1.observation().data.len(), Observation::SIZE);
+    assert_eq!(env1.action_mask().len(), ACTION_SPACE);
+    for _ in 0..20 {
+        let action = env1.action_mask().iter().position(|&legal| legal);
+        let Some(action) = action else {
+            break;
+        };
+        let (obs1, reward1, done1, _) = env1.step(action);
+        let (obs2, reward2, done2, _) = env2.step(action);
+        assert_eq!(obs1, obs2);
+        assert_eq!(reward1, reward2);
+        assert_eq!(done1, done2);
+        assert_eq!(reward1, 1.0);
+    }
+}
+
+#[test]
+fn test_wide_offers_fit_the_spaces() {
+    let mut env = Environment::new(3);
+    let offset = Observation::SIZE - MAX_OFFER_SIZE * SLOT_SIZE;
+    let padding = |env: &Environment, slot: usize| {
+        env.observation().data[offset + slot * SLOT_SIZE + Building::ALL.len()]
+    };
+    assert_eq!(env.game.offer.len(), 2);
+    assert_eq!(padding(&env, 1), 0.0);
+    assert_eq!(padding(&env, 2), 1.0);
+    assert!(!env.action_mask()[2]);
+
+    env.game.offer.push(Building::Forest);
+    let observation = env.observation();
+    assert_eq!(padding(&env, 2), 0.0);
+    assert_eq!(
+        observation.data[offset + 2 * SLOT_SIZE + Building::Forest.index()],
+        1.0
+    );
+    assert_eq!(env.action_mask().len(), ACTION_SPACE);
+}
@@ -0,0 +1,156 @@
+//! Random events that can happen between two turns. They are drawn from the
+//! game's seeded generator, so a replay with the same seed and the same
+//! choices goes through the same events.
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::buildings::Building;
+use crate::states::State;
+
+/// Percent chance of each event, when its conditions are met.
+const EVENT_CHANCE: u32 = 5;
+/// Percent chance of a fire spreading to each adjacent forest.
+const FIRE_SPREAD_CHANCE: u32 = 50;
+const STORM_TURNS: u32 = 3;
+const BOOM_TURNS: u32 = 3;
+const SETTLERS: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Forests burnt down to rubble, the fire spreading from one forest to
+    /// the forests next to it.
+    Fire { burnt: Vec<(i32, i32)> },
+    /// Wood production is halved for `turns` turns.
+    Storm { turns: u32 },
+    /// Quarries produce one more rock for `turns` turns.
+    MiningBoom { turns: u32 },
+    /// People moving in at once, if there is room.
+    Settlers { people: u32 },
+}
+
+impl Event {
+    /// Draw the event happening after this turn, if any. At most one event
+    /// happens per turn, and only events whose conditions are met can.
+    pub fn roll<R: Rng>(state: &State, rng: &mut R) -> Option<Self> {
+        let mut forests: Vec<(i32, i32)> = cells_of(state, Building::Forest);
+        forests.sort();
+        let has_quarry = !cells_of(state, Building::Quarry).is_empty();
+        let resources = &state.owned_resources;
+        let room = resources.housing.saturating_sub(resources.total_people);
+
+        let mut candidates = vec![Event::Storm { turns: STORM_TURNS }];
+        if !forests.is_empty() {
+            candidates.push(Event::Fire { burnt: vec![] });
+        }
+        if has_quarry {
+            candidates.push(Event::MiningBoom { turns: BOOM_TURNS });
+        }
+        if room > 0 {
+            candidates.push(Event::Settlers {
+                people: room.min(SETTLERS),
+            });
+        }
+
+        let draw = rng.gen_range(0..100);
+        let event = candidates.into_iter().nth((draw / EVENT_CHANCE) as usize)?;
+        match event {
+            Event::Fire { .. } => {
+                let origin = forests[rng.gen_range(0..forests.len())];
+                Some(Event::Fire {
+                    burnt: spread_fire(state, origin, rng),
+                })
+            }
+            event => Some(event),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Event::Fire { burnt } => format!("🔥 A fire burnt down {} forest(s)", burnt.len()),
+            Event::Storm { turns } => {
+                format!("🌩️ A storm halves wood production for {turns} turns")
+            }
+            Event::MiningBoom { turns } => {
+                format!("⛏️ A mining boom, quarries produce +1 rock for {turns} turns")
+            }
+            Event::Settlers { people } => format!("🧳 {people} settler(s) moved in"),
+        }
+    }
+
+    pub fn apply(&self, state: &mut State) {
+        match self {
+            Event::Fire { burnt } => {
+                for &coordinates in burnt {
+                    state.destroy(coordinates);
+                }
+            }
+            Event::Storm { turns } => state.storm_turns = *turns,
+            Event::MiningBoom { turns } => state.boom_turns = *turns,
+            Event::Settlers { people } => {
+                let resources = &mut state.owned_resources;
+                resources.total_people = (resources.total_people + people).min(resources.housing);
+            }
+        }
+    }
+}
+
+fn cells_of(state: &State, kind: Building) -> Vec<(i32, i32)> {
+    state
+        .spiral
+        .iter()
+        .filter(|&(_, &building)| building == kind)
+        .map(|(&coordinates, _)| coordinates)
+        .collect()
+}
+
+/// Forests reached by a fire starting at `origin`, in the order they burn.
+fn spread_fire<R: Rng>(state: &State, origin: (i32, i32), rng: &mut R) -> Vec<(i32, i32)> {
+    let mut burnt = vec![origin];
+    let mut i = 0;
+    while i < burnt.len() {
+        let (x, y) = burnt[i];
+        for neighbour in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if state.spiral.get(&neighbour) == Some(&Building::Forest)
+                && !burnt.contains(&neighbour)
+                && rng.gen_range(0..100) < FIRE_SPREAD_CHANCE
+            {
+                burnt.push(neighbour);
+            }
+        }
+        i += 1;
+    }
+    burnt
+}
+
+#[test]
+fn test_events() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let mut state = State::initialize();
+    state.spiral.insert((1, 0), Building::Forest);
+    state.spiral.insert((2, 0), Building::Forest);
+    Event::Fire {
+        burnt: vec![(1, 0), (2, 0)],
+    }
+    .apply(&mut state);
+    assert!(state.rubble.contains(&(1, 0)) && state.rubble.contains(&(2, 0)));
+    assert_eq!(state.spiral.len(), 1);
+
+    Event::Settlers { people: 2 }.apply(&mut state);
+    assert_eq!(state.owned_resources.total_people, 2);
+
+    // Without room, forests or quarries, only storms can happen
+    let mut rng = StdRng::seed_from_u64(0);
+    let events: Vec<Event> = (0..200)
+        .filter_map(|_| Event::roll(&state, &mut rng))
+        .collect();
+    assert!(!events.is_empty());
+    assert!(
+        events
+            .iter()
+            .all(|event| matches!(event, Event::Storm { .. }))
+    );
+}
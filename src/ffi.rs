@@ -0,0 +1,736 @@
+//! C ABI over `Game`, see `include/spiral_city.h`.
+//!
+//! Buildings are exchanged as their index in `Building::ALL`, and -1 stands
+//! for "no building". Strings returned by the library must be released with
+//! `spiral_city_string_free`.
+
+use std::ffi::{CString, c_char};
+use std::ptr;
+
+use serde_json::json;
+
+use crate::buildings::Building;
+use crate::districts;
+use crate::events::Event;
+use crate::fields::Field;
+use crate::game::{Action, Game};
+use crate::perks::Perk;
+use crate::protocol::StateView;
+use crate::resources::Good;
+
+pub const SPIRAL_CITY_NO_BUILDING: i32 = -1;
+pub const SPIRAL_CITY_RUBBLE: i32 = -2;
+pub const SPIRAL_CITY_HOUSE: i32 = 0;
+pub const SPIRAL_CITY_FOREST: i32 = 1;
+pub const SPIRAL_CITY_QUARRY: i32 = 2;
+pub const SPIRAL_CITY_WORKSHOP: i32 = 3;
+pub const SPIRAL_CITY_FARM: i32 = 4;
+pub const SPIRAL_CITY_FISHERY: i32 = 5;
+pub const SPIRAL_CITY_WAREHOUSE: i32 = 6;
+pub const SPIRAL_CITY_MARKET: i32 = 7;
+pub const SPIRAL_CITY_COLOSSUS: i32 = 8;
+pub const SPIRAL_CITY_GARDENS: i32 = 9;
+
+pub const SPIRAL_CITY_NO_EVENT: i32 = -1;
+pub const SPIRAL_CITY_FIRE: i32 = 0;
+pub const SPIRAL_CITY_STORM: i32 = 1;
+pub const SPIRAL_CITY_MINING_BOOM: i32 = 2;
+pub const SPIRAL_CITY_SETTLERS: i32 = 3;
+
+pub const SPIRAL_CITY_PLAINS: i32 = 0;
+pub const SPIRAL_CITY_HILLS: i32 = 1;
+pub const SPIRAL_CITY_RIVER: i32 = 2;
+pub const SPIRAL_CITY_MOUNTAIN: i32 = 3;
+pub const SPIRAL_CITY_FERTILE: i32 = 4;
+
+pub const SPIRAL_CITY_NO_DISTRICT: i32 = -1;
+pub const SPIRAL_CITY_WOODLAND: i32 = 0;
+pub const SPIRAL_CITY_MINES: i32 = 1;
+pub const SPIRAL_CITY_FARMLAND: i32 = 2;
+pub const SPIRAL_CITY_HARBOUR: i32 = 3;
+
+pub const SPIRAL_CITY_WOOD: i32 = 0;
+pub const SPIRAL_CITY_ROCK: i32 = 1;
+pub const SPIRAL_CITY_FOOD: i32 = 2;
+
+pub const SPIRAL_CITY_NO_PERK: i32 = -1;
+pub const SPIRAL_CITY_WORKSHOP_HOUSES: i32 = 0;
+pub const SPIRAL_CITY_HEAD_START: i32 = 1;
+pub const SPIRAL_CITY_WIDE_OFFERS: i32 = 2;
+pub const SPIRAL_CITY_HARMONY: i32 = 3;
+pub const SPIRAL_CITY_MASONRY: i32 = 4;
+
+pub const SPIRAL_CITY_POLLUTION: i32 = 0;
+pub const SPIRAL_CITY_HAPPINESS: i32 = 1;
+
+/// Opaque handle on a game.
+pub struct SpiralCityGame {
+    game: Game,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpiralCityResources {
+    pub total_people: u32,
+    pub occupied_people: u32,
+    pub housing: u32,
+    pub wood: u32,
+    pub rock: u32,
+    pub food: u32,
+    pub hungry_people: u32,
+    pub wood_cap: u32,
+    pub rock_cap: u32,
+    pub food_cap: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpiralCityBounds {
+    pub x_min: i32,
+    pub x_max: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+}
+
+fn building_code(building: Option<&Building>) -> i32 {
+    building.map_or(-1, |b| b.index() as i32)
+}
+
+fn good_from_code(code: i32) -> Option<Good> {
+    usize::try_from(code)
+        .ok()
+        .and_then(|index| Good::ALL.get(index))
+        .copied()
+}
+
+/// Create a new game. Release it with `spiral_city_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn spiral_city_new(seed: u64) -> *mut SpiralCityGame {
+    Box::into_raw(Box::new(SpiralCityGame {
+        game: Game::new(seed),
+    }))
+}
+
+/// Create a new game where the spiral can be steered at its corners and a
+/// few cells can be skipped. Release it with `spiral_city_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn spiral_city_new_steered(seed: u64) -> *mut SpiralCityGame {
+    Box::into_raw(Box::new(SpiralCityGame {
+        game: Game::with_steering(seed),
+    }))
+}
+
+/// Create a new game where buildings go on any empty cell next to the city.
+/// Release it with `spiral_city_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn spiral_city_new_frontier(seed: u64) -> *mut SpiralCityGame {
+    Box::into_raw(Box::new(SpiralCityGame {
+        game: Game::with_frontier(seed),
+    }))
+}
+
+/// # Safety
+/// `game` must be null or a pointer returned by `spiral_city_new` that has
+/// not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_free(game: *mut SpiralCityGame) {
+    if !game.is_null() {
+        drop(unsafe { Box::from_raw(game) });
+    }
+}
+
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_turn(game: *const SpiralCityGame) -> u32 {
+    unsafe { &(*game).game }.state.turn
+}
+
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_is_over(game: *const SpiralCityGame) -> bool {
+    unsafe { &(*game).game }.is_over()
+}
+
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_offer_len(game: *const SpiralCityGame) -> usize {
+    unsafe { &(*game).game }.offer.len()
+}
+
+/// Building offered at `index`, or -1 if `index` is out of the offer.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_offer_get(game: *const SpiralCityGame, index: usize) -> i32 {
+    building_code(unsafe { &(*game).game }.offer.get(index))
+}
+
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_is_legal(game: *const SpiralCityGame, index: usize) -> bool {
+    let game = unsafe { &(*game).game };
+    game.legal_moves().get(index).copied().unwrap_or(false)
+}
+
+/// Build the offered building at `index`. Returns false, leaving the game
+/// unchanged, if that choice is not legal.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_choose(game: *mut SpiralCityGame, index: usize) -> bool {
+    unsafe { &mut (*game).game }.choose(index).is_ok()
+}
+
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_resources(game: *const SpiralCityGame) -> SpiralCityResources {
+    let resources = &unsafe { &(*game).game }.state.owned_resources;
+    SpiralCityResources {
+        total_people: resources.total_people,
+        occupied_people: resources.occupied_people,
+        housing: resources.housing,
+        wood: resources.wood,
+        rock: resources.rock,
+        food: resources.food,
+        hungry_people: resources.hungry_people,
+        wood_cap: resources.wood_cap,
+        rock_cap: resources.rock_cap,
+        food_cap: resources.food_cap,
+    }
+}
+
+/// Bounds of the built part of the spiral, inclusive.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_bounds(game: *const SpiralCityGame) -> SpiralCityBounds {
+    let state = &unsafe { &(*game).game }.state;
+    SpiralCityBounds {
+        x_min: state.x_bounds.0,
+        x_max: state.x_bounds.1,
+        y_min: state.y_bounds.0,
+        y_max: state.y_bounds.1,
+    }
+}
+
+/// Building standing on cell (x, y), -2 for rubble or -1 if the cell is
+/// empty.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_cell(game: *const SpiralCityGame, x: i32, y: i32) -> i32 {
+    let state = &unsafe { &(*game).game }.state;
+    if state.rubble.contains(&(x, y)) {
+        return SPIRAL_CITY_RUBBLE;
+    }
+    building_code(state.spiral.get(&(x, y)))
+}
+
+/// Terrain of cell (x, y), built or not.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_terrain(game: *const SpiralCityGame, x: i32, y: i32) -> i32 {
+    unsafe { &(*game).game }.state.terrain((x, y)).index() as i32
+}
+
+/// District the building on cell (x, y) belongs to, or
+/// `SPIRAL_CITY_NO_DISTRICT`.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_district(game: *const SpiralCityGame, x: i32, y: i32) -> i32 {
+    let state = &unsafe { &(*game).game }.state;
+    districts::district_at(state, (x, y))
+        .map_or(SPIRAL_CITY_NO_DISTRICT, |district| district.index() as i32)
+}
+
+/// Demolish the building on cell (x, y), leaving rubble. Returns false,
+/// leaving the game unchanged, if that is not possible.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_demolish(game: *mut SpiralCityGame, x: i32, y: i32) -> bool {
+    unsafe { &mut (*game).game }
+        .play(Action::Demolish { x, y })
+        .is_ok()
+}
+
+/// Build the offered building at `index` on the rubble at (x, y). Returns
+/// false, leaving the game unchanged, if that choice is not legal.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_rebuild(
+    game: *mut SpiralCityGame,
+    index: usize,
+    x: i32,
+    y: i32,
+) -> bool {
+    unsafe { &mut (*game).game }
+        .play(Action::Rebuild { index, x, y })
+        .is_ok()
+}
+
+/// Upgrade the building on cell (x, y) to its next tier, which takes the
+/// turn. Returns false, leaving the game unchanged, if that is not possible.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_upgrade(game: *mut SpiralCityGame, x: i32, y: i32) -> bool {
+    unsafe { &mut (*game).game }
+        .play(Action::Upgrade { x, y })
+        .is_ok()
+}
+
+/// Staff the given kind of building first. This does not end the turn.
+/// Returns false, leaving the game unchanged, if `building` is not a
+/// building code or does not need workers.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_prioritise(game: *mut SpiralCityGame, building: i32) -> bool {
+    let Some(&building) = usize::try_from(building)
+        .ok()
+        .and_then(|index| Building::ALL.get(index))
+    else {
+        return false;
+    };
+    unsafe { &mut (*game).game }
+        .play(Action::Prioritise { building })
+        .is_ok()
+}
+
+/// Number of workers assigned to the building on cell (x, y) this turn.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_cell_workers(
+    game: *const SpiralCityGame,
+    x: i32,
+    y: i32,
+) -> u32 {
+    unsafe { &(*game).game }.state.workers_on((x, y))
+}
+
+/// Buy `amount` units of the good `get` at the market, paying with the good
+/// `give` (`SPIRAL_CITY_WOOD`, `SPIRAL_CITY_ROCK` or `SPIRAL_CITY_FOOD`).
+/// This does not end the turn. Returns false, leaving the game unchanged,
+/// if that trade is not possible.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_trade(
+    game: *mut SpiralCityGame,
+    give: i32,
+    get: i32,
+    amount: u32,
+) -> bool {
+    let (Some(give), Some(get)) = (good_from_code(give), good_from_code(get)) else {
+        return false;
+    };
+    unsafe { &mut (*game).game }
+        .play(Action::Trade { give, get, amount })
+        .is_ok()
+}
+
+/// Build the offered building at `index` on the empty cell (x, y) next to
+/// the city, in a game created with `spiral_city_new_frontier`. Returns
+/// false, leaving the game unchanged, if that is not possible.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_build_at(
+    game: *mut SpiralCityGame,
+    index: usize,
+    x: i32,
+    y: i32,
+) -> bool {
+    unsafe { &mut (*game).game }
+        .play(Action::BuildAt { index, x, y })
+        .is_ok()
+}
+
+/// Make the spiral turn the other way at the coming corner. This does not
+/// end the turn. Returns false if the game is not steered or the next cell
+/// is not a corner.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_steer(game: *mut SpiralCityGame) -> bool {
+    unsafe { &mut (*game).game }.play(Action::Steer).is_ok()
+}
+
+/// Leave the next cell empty for later, using a skip token. This does not
+/// end the turn. Returns false if no skip token is left.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_skip(game: *mut SpiralCityGame) -> bool {
+    unsafe { &mut (*game).game }.play(Action::Skip).is_ok()
+}
+
+/// Skip tokens left.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_skip_tokens(game: *const SpiralCityGame) -> u32 {
+    unsafe { &(*game).game }.state.skip_tokens
+}
+
+/// Perk proposed at `index` of the draft, or `SPIRAL_CITY_NO_PERK` if
+/// `index` is out of the draft or the draft is over.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_perk_draft_get(
+    game: *const SpiralCityGame,
+    index: usize,
+) -> i32 {
+    let state = &unsafe { &(*game).game }.state;
+    state
+        .perk_draft
+        .get(index)
+        .map_or(SPIRAL_CITY_NO_PERK, |perk| perk.index() as i32)
+}
+
+/// Keep the drafted perk at `index` for the whole run. This does not end
+/// the turn. Returns false after the first turn or for a wrong index.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_pick_perk(game: *mut SpiralCityGame, index: usize) -> bool {
+    unsafe { &mut (*game).game }
+        .play(Action::PickPerk { index })
+        .is_ok()
+}
+
+/// Whether `perk` applies to this run.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_has_perk(game: *const SpiralCityGame, perk: i32) -> bool {
+    let state = &unsafe { &(*game).game }.state;
+    usize::try_from(perk)
+        .ok()
+        .and_then(|index| Perk::ALL.get(index))
+        .is_some_and(|perk| state.perks.contains(perk))
+}
+
+/// Draw a new offer, using a reroll. This does not end the turn. Returns
+/// false if no reroll is left.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_reroll(game: *mut SpiralCityGame) -> bool {
+    unsafe { &mut (*game).game }.play(Action::Reroll).is_ok()
+}
+
+/// Rerolls left.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_rerolls(game: *const SpiralCityGame) -> u32 {
+    unsafe { &(*game).game }.state.rerolls
+}
+
+/// Turn at the end of which the next tribute is taken.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_tribute_due(game: *const SpiralCityGame) -> u32 {
+    unsafe { &(*game).game }.state.tribute.due
+}
+
+/// Amount of `good` demanded by the next tribute, or -1 for an unknown good.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_tribute(game: *const SpiralCityGame, good: i32) -> i32 {
+    let state = &unsafe { &(*game).game }.state;
+    good_from_code(good).map_or(-1, |good| state.tribute.amount(good) as i32)
+}
+
+/// Take on the contract offered. This does not end the turn. Returns false
+/// if no contract is offered or too many are running.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_accept_contract(game: *mut SpiralCityGame) -> bool {
+    unsafe { &mut (*game).game }.play(Action::Accept).is_ok()
+}
+
+/// Contracts accepted and not settled yet.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_contracts(game: *const SpiralCityGame) -> usize {
+    unsafe { &(*game).game }.state.contracts.len()
+}
+
+/// Price of the next unit of `good` at the market, or -1 without a market
+/// or for an unknown good.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_trade_price(game: *const SpiralCityGame, good: i32) -> i32 {
+    let state = &unsafe { &(*game).game }.state;
+    good_from_code(good)
+        .and_then(|good| state.trade_price(good))
+        .map_or(-1, |price| price as i32)
+}
+
+/// Event that happened after the last turn, `SPIRAL_CITY_NO_EVENT` if none.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_last_event(game: *const SpiralCityGame) -> i32 {
+    let game = unsafe { &(*game).game };
+    match game.history.last().and_then(|record| record.event.as_ref()) {
+        None => SPIRAL_CITY_NO_EVENT,
+        Some(Event::Fire { .. }) => SPIRAL_CITY_FIRE,
+        Some(Event::Storm { .. }) => SPIRAL_CITY_STORM,
+        Some(Event::MiningBoom { .. }) => SPIRAL_CITY_MINING_BOOM,
+        Some(Event::Settlers { .. }) => SPIRAL_CITY_SETTLERS,
+    }
+}
+
+/// Value of a field (`SPIRAL_CITY_POLLUTION` or `SPIRAL_CITY_HAPPINESS`) on
+/// cell (x, y), or 0 for an unknown field.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_field(
+    game: *const SpiralCityGame,
+    field: i32,
+    x: i32,
+    y: i32,
+) -> i32 {
+    let state = &unsafe { &(*game).game }.state;
+    usize::try_from(field)
+        .ok()
+        .and_then(|index| Field::ALL.get(index))
+        .map_or(0, |field| field.value_at(state, (x, y)))
+}
+
+/// City-wide happiness, the average over the houses.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_happiness(game: *const SpiralCityGame) -> i32 {
+    unsafe { &(*game).game }.state.happiness
+}
+
+/// Rings of the spiral completed so far.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_rings(game: *const SpiralCityGame) -> u32 {
+    unsafe { &(*game).game }.state.rings
+}
+
+/// Buildings that can still be placed for free, earned by completing rings.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_free_buildings(game: *const SpiralCityGame) -> u32 {
+    unsafe { &(*game).game }.state.free_buildings
+}
+
+/// Tier of the building on cell (x, y), from 1, or 0 if the cell is empty.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_cell_level(
+    game: *const SpiralCityGame,
+    x: i32,
+    y: i32,
+) -> u32 {
+    let state = &unsafe { &(*game).game }.state;
+    if state.spiral.contains_key(&(x, y)) {
+        state.level((x, y))
+    } else {
+        0
+    }
+}
+
+/// Whether the building on cell (x, y) paid its upkeep this turn. Empty
+/// cells are reported as inactive.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_cell_is_active(
+    game: *const SpiralCityGame,
+    x: i32,
+    y: i32,
+) -> bool {
+    let state = &unsafe { &(*game).game }.state;
+    state.spiral.contains_key(&(x, y)) && !state.inactive.contains(&(x, y))
+}
+
+/// Write the position where the next building will be placed.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`, `x` and `y`
+/// must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_next_position(
+    game: *const SpiralCityGame,
+    x: *mut i32,
+    y: *mut i32,
+) {
+    let (nx, ny) = unsafe { &(*game).game }.state.get_next_position();
+    unsafe {
+        *x = nx;
+        *y = ny;
+    }
+}
+
+/// Serialise the state, offer and legal moves as a JSON string.
+///
+/// # Safety
+/// `game` must be a live pointer returned by `spiral_city_new`. The result
+/// must be released with `spiral_city_string_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_serialize(game: *const SpiralCityGame) -> *mut c_char {
+    let game = unsafe { &(*game).game };
+    let value = json!({
+        "seed": game.seed,
+        "state": StateView::from_state(&game.state),
+        "offer": game.offer,
+        "legal_moves": game.legal_moves(),
+        "history": game.history,
+    });
+    match CString::new(value.to_string()) {
+        Ok(string) => string.into_raw(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `string` must be null or a pointer returned by `spiral_city_serialize`
+/// that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spiral_city_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+#[test]
+fn test_ffi_building_codes() {
+    let codes = [
+        SPIRAL_CITY_HOUSE,
+        SPIRAL_CITY_FOREST,
+        SPIRAL_CITY_QUARRY,
+        SPIRAL_CITY_WORKSHOP,
+        SPIRAL_CITY_FARM,
+        SPIRAL_CITY_FISHERY,
+        SPIRAL_CITY_WAREHOUSE,
+        SPIRAL_CITY_MARKET,
+        SPIRAL_CITY_COLOSSUS,
+        SPIRAL_CITY_GARDENS,
+    ];
+    assert_eq!(codes.len(), Building::ALL.len());
+    for (building, code) in Building::ALL.iter().zip(codes) {
+        assert_eq!(building_code(Some(building)), code);
+    }
+}
+
+#[test]
+fn test_ffi_round_trip() {
+    let game = spiral_city_new(7);
+    unsafe {
+        assert_eq!(spiral_city_turn(game), 0);
+        assert_eq!(spiral_city_cell(game, 0, 0), SPIRAL_CITY_HOUSE);
+        assert_eq!(spiral_city_cell(game, 4, 4), SPIRAL_CITY_NO_BUILDING);
+        assert_eq!(spiral_city_terrain(game, 0, 0), SPIRAL_CITY_PLAINS);
+        assert_eq!(spiral_city_district(game, 0, 0), SPIRAL_CITY_NO_DISTRICT);
+        assert_eq!(spiral_city_cell_level(game, 0, 0), 1);
+        assert_eq!(spiral_city_cell_level(game, 4, 4), 0);
+        assert!(!spiral_city_upgrade(game, 0, 0));
+        assert_eq!(spiral_city_field(game, SPIRAL_CITY_POLLUTION, 0, 0), 0);
+        assert_eq!(spiral_city_happiness(game), 0);
+        assert_eq!(spiral_city_rings(game), 0);
+        assert_eq!(spiral_city_free_buildings(game), 0);
+        assert!(!spiral_city_steer(game));
+        assert!(!spiral_city_skip(game));
+        assert!(!spiral_city_build_at(game, 0, 0, 1));
+        assert_eq!(spiral_city_skip_tokens(game), 0);
+        assert!(!spiral_city_accept_contract(game));
+        assert_eq!(spiral_city_contracts(game), 0);
+        assert_eq!(spiral_city_tribute_due(game), 10);
+        assert!(!spiral_city_reroll(game));
+        let perk = spiral_city_perk_draft_get(game, 0);
+        assert_ne!(perk, SPIRAL_CITY_NO_PERK);
+        assert!(!spiral_city_has_perk(game, perk));
+        assert!(!spiral_city_pick_perk(game, 3));
+        assert_eq!(spiral_city_rerolls(game), 0);
+        assert_eq!(spiral_city_tribute(game, SPIRAL_CITY_WOOD), 10);
+        assert_eq!(spiral_city_tribute(game, 3), -1);
+        assert_eq!(spiral_city_last_event(game), SPIRAL_CITY_NO_EVENT);
+        assert_eq!(spiral_city_trade_price(game, SPIRAL_CITY_ROCK), -1);
+        assert!(!spiral_city_trade(
+            game,
+            SPIRAL_CITY_FOOD,
+            SPIRAL_CITY_ROCK,
+            1
+        ));
+        assert!(!spiral_city_prioritise(game, SPIRAL_CITY_HOUSE));
+        assert!(!spiral_city_prioritise(game, SPIRAL_CITY_NO_BUILDING));
+        assert!(spiral_city_prioritise(game, SPIRAL_CITY_QUARRY));
+        let index = (0..spiral_city_offer_len(game))
+            .find(|&i| spiral_city_is_legal(game, i))
+            .unwrap();
+        assert!(!spiral_city_choose(game, 99));
+        assert!(spiral_city_choose(game, index));
+        assert_eq!(spiral_city_turn(game), 1);
+        let (mut x, mut y) = (0, 0);
+        spiral_city_next_position(game, &mut x, &mut y);
+        assert_eq!((x, y), (1, -1));
+
+        let json = spiral_city_serialize(game);
+        let text = std::ffi::CStr::from_ptr(json).to_str().unwrap().to_string();
+        spiral_city_string_free(json);
+        assert!(text.contains("\"turn\":1"));
+        spiral_city_free(game);
+    }
+}
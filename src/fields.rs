@@ -0,0 +1,146 @@
+//! Environmental values computed for every cell from the buildings around
+//! it, and shown as heat-map overlays on the spiral.
+
+use serde::{Deserialize, Serialize};
+
+use crate::buildings::Building;
+use crate::states::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Field {
+    Pollution,
+    Happiness,
+}
+
+impl Field {
+    pub const ALL: [Field; 2] = [Field::Pollution, Field::Happiness];
+
+    /// Field whose name (e.g. "pollution") is given, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|field| format!("{field:?}").eq_ignore_ascii_case(name))
+    }
+
+    pub fn value_at(&self, state: &State, coordinates: (i32, i32)) -> i32 {
+        match *self {
+            Field::Pollution => pollution_at(state, coordinates),
+            Field::Happiness => happiness_at(state, coordinates),
+        }
+    }
+
+    /// Heat-map square for a value, from green (good) to red (bad).
+    pub fn heat_char(&self, value: i32) -> char {
+        let badness = match *self {
+            Field::Pollution => value,
+            Field::Happiness => -value,
+        };
+        match badness {
+            ..=-1 => '🟩',
+            0 => '🟨',
+            1..=2 => '🟧',
+            _ => '🟥',
+        }
+    }
+
+    pub fn legend(&self) -> &'static str {
+        match *self {
+            Field::Pollution => "🟨 none | 🟧 1-2 | 🟥 3+",
+            Field::Happiness => "🟩 happy | 🟨 neutral | 🟧 -1 to -2 | 🟥 -3 or less",
+        }
+    }
+}
+
+/// Distance within which the Hanging Gardens make houses happier.
+pub const GARDENS_RADIUS: i32 = 3;
+
+/// Pollution emitted (or absorbed, if negative) on the building's own cell.
+/// It fades by one per cell of distance.
+fn emission(building: Building) -> i32 {
+    match building {
+        Building::Quarry => 3,
+        Building::Workshop => 4,
+        Building::Forest => -3,
+        _ => 0,
+    }
+}
+
+/// Quarries and workshops pollute the cells around them, forests absorb
+/// pollution. Inactive buildings do not pollute.
+pub fn pollution_at(state: &State, coordinates: (i32, i32)) -> i32 {
+    let (x, y) = coordinates;
+    let mut pollution = 0;
+    for (&(bx, by), &building) in &state.spiral {
+        let strength = emission(building);
+        if strength > 0 && state.inactive.contains(&(bx, by)) {
+            continue;
+        }
+        let distance = (bx - x).abs() + (by - y).abs();
+        pollution += strength.signum() * (strength.abs() - distance).max(0);
+    }
+    pollution.max(0)
+}
+
+/// Happiness of someone living on this cell: forests and farms next door
+/// are pleasant, quarries and workshops are noisy, pollution hurts and the
+/// Hanging Gardens nearby delight. Inactive neighbours do not count.
+pub fn happiness_at(state: &State, coordinates: (i32, i32)) -> i32 {
+    let (x, y) = coordinates;
+    let mut happiness = 0;
+    let near_gardens = state.spiral.iter().any(|(&(gx, gy), &building)| {
+        building == Building::Gardens && (gx - x).abs() + (gy - y).abs() <= GARDENS_RADIUS
+    });
+    if near_gardens {
+        happiness += 2;
+    }
+    for neighbour in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+        if state.inactive.contains(&neighbour) {
+            continue;
+        }
+        match state.spiral.get(&neighbour) {
+            Some(Building::Forest) | Some(Building::Farm) => happiness += 1,
+            Some(Building::Quarry) | Some(Building::Workshop) => happiness -= 1,
+            _ => {}
+        }
+    }
+    happiness - pollution_at(state, coordinates) / 2
+}
+
+/// Average happiness over the houses of the city.
+pub fn city_happiness(state: &State) -> i32 {
+    let houses: Vec<(i32, i32)> = state
+        .spiral
+        .iter()
+        .filter(|&(_, &building)| building == Building::House)
+        .map(|(&coordinates, _)| coordinates)
+        .collect();
+    if houses.is_empty() {
+        return 0;
+    }
+    let total: i32 = houses
+        .iter()
+        .map(|&coordinates| happiness_at(state, coordinates))
+        .sum();
+    total / houses.len() as i32
+}
+
+#[test]
+fn test_pollution_and_happiness() {
+    let mut state = State::initialize();
+    state.spiral.insert((2, 0), Building::Quarry);
+    assert_eq!(pollution_at(&state, (2, 0)), 3);
+    assert_eq!(pollution_at(&state, (0, 0)), 1);
+    assert_eq!(pollution_at(&state, (-1, 0)), 0);
+    assert_eq!(happiness_at(&state, (1, 0)), -2);
+
+    state.spiral.insert((1, 0), Building::Forest);
+    assert_eq!(pollution_at(&state, (0, 0)), 0);
+    assert_eq!(happiness_at(&state, (0, 0)), 1);
+    assert_eq!(city_happiness(&state), 1);
+    assert_eq!(Field::Happiness.heat_char(1), '🟩');
+    assert_eq!(Field::from_name("POLLUTION"), Some(Field::Pollution));
+
+    // An inactive quarry is neither noisy nor polluting
+    state.inactive.insert((2, 0));
+    assert_eq!(happiness_at(&state, (3, 0)), 0);
+}
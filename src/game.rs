@@ -0,0 +1,407 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::buildings::Building;
+use crate::contracts::{CONTRACT_INTERVAL, Contract};
+use crate::events::Event;
+use crate::milestones::{self, Reward};
+use crate::perks::{self, Perk};
+use crate::profiles::Profile;
+use crate::resources::{Good, Resources};
+use crate::states::{SKIP_TOKENS, State};
+
+/// What the player can do with the current offer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Build the offered building at `index` on the next spiral cell.
+    Build { index: usize },
+    /// Build the offered building at `index` on the rubble at (x, y).
+    Rebuild { index: usize, x: i32, y: i32 },
+    /// Build the offered building at `index` on the frontier cell (x, y), in
+    /// frontier mode.
+    BuildAt { index: usize, x: i32, y: i32 },
+    /// Demolish the building at (x, y). This does not end the turn.
+    Demolish { x: i32, y: i32 },
+    /// Upgrade the building at (x, y) to its next tier instead of building.
+    Upgrade { x: i32, y: i32 },
+    /// Staff this kind of building first. This does not end the turn.
+    Prioritise { building: Building },
+    /// Buy `amount` units of `get` at the market, paying with `give`. This
+    /// does not end the turn.
+    Trade { give: Good, get: Good, amount: u32 },
+    /// Turn the other way at the coming corner. This does not end the turn.
+    Steer,
+    /// Leave the next cell empty for later. This does not end the turn.
+    Skip,
+    /// Take on the contract offered. This does not end the turn.
+    Accept,
+    /// Draw a new offer instead of this one. This does not end the turn.
+    Reroll,
+    /// Keep the drafted perk at `index` for the whole run, before the first
+    /// turn. This does not end the turn.
+    PickPerk { index: usize },
+}
+
+impl Action {
+    /// Parse a terminal command: `n` builds the n-th offered building,
+    /// `D x y` demolishes, `U x y` upgrades, `P name` staffs a kind of
+    /// building first, `T give get n` trades at the market, `W` steers, `S`
+    /// skips the next cell, `A` accepts the contract offered, `N` rerolls
+    /// the offer, `R n x y` builds the n-th offered building on rubble or a
+    /// skipped cell and `B n x y` builds it on a frontier cell. Building
+    /// numbers start at 1.
+    pub fn parse(command: &str) -> Option<Self> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        if let [p, name] = words[..]
+            && p.eq_ignore_ascii_case("P")
+        {
+            let building = Building::from_name(name)?;
+            return Some(Action::Prioritise { building });
+        }
+        if let [t, give, get, amount] = words[..]
+            && t.eq_ignore_ascii_case("T")
+        {
+            return Some(Action::Trade {
+                give: Good::from_name(give)?,
+                get: Good::from_name(get)?,
+                amount: amount.parse().ok()?,
+            });
+        }
+        let numbers: Vec<i32> = words
+            .iter()
+            .skip(1)
+            .map(|word| word.parse().ok())
+            .collect::<Option<_>>()?;
+        match (words.first()?.to_ascii_uppercase().as_str(), &numbers[..]) {
+            ("W", []) => Some(Action::Steer),
+            ("S", []) => Some(Action::Skip),
+            ("A", []) => Some(Action::Accept),
+            ("N", []) => Some(Action::Reroll),
+            ("D", &[x, y]) => Some(Action::Demolish { x, y }),
+            ("U", &[x, y]) => Some(Action::Upgrade { x, y }),
+            ("R", &[n, x, y]) if n >= 1 => Some(Action::Rebuild {
+                index: n as usize - 1,
+                x,
+                y,
+            }),
+            ("B", &[n, x, y]) if n >= 1 => Some(Action::BuildAt {
+                index: n as usize - 1,
+                x,
+                y,
+            }),
+            (n, []) => match n.parse::<usize>() {
+                Ok(n) if n >= 1 => Some(Action::Build { index: n - 1 }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// What happened during one turn of a `Game`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnRecord {
+    pub turn: u32,
+    pub offer: Vec<Building>,
+    pub built: Building,
+    /// Tier of `built` after the turn, above 1 for upgrades.
+    pub level: u32,
+    pub position: (i32, i32),
+    pub delta_production: Vec<Resources>,
+    pub lost_production: Vec<Resources>,
+    /// What happened between this turn and the next one.
+    pub event: Option<Event>,
+    /// Ring of the spiral completed by this turn, if any.
+    pub ring: Option<u32>,
+    /// What completing `ring` gave.
+    pub rewards: Vec<Reward>,
+    /// Contracts settled by this turn, with whether they were met.
+    pub contracts: Vec<(Contract, bool)>,
+}
+
+/// A seeded game: the engine state, the current offer and the generator
+/// used to draw the next offers. Two games created with the same seed and
+/// given the same choices always play out identically.
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub seed: u64,
+    pub state: State,
+    pub offer: Vec<Building>,
+    pub history: Vec<TurnRecord>,
+    rng: StdRng,
+}
+
+impl Game {
+    pub fn new(seed: u64) -> Self {
+        Self::start(
+            seed,
+            State {
+                terrain_seed: Some(seed),
+                ..State::initialize()
+            },
+        )
+    }
+
+    /// A seeded game where the player steers the spiral at its corners and
+    /// can skip a few cells.
+    pub fn with_steering(seed: u64) -> Self {
+        let mut game = Self::new(seed);
+        game.state.steering = true;
+        game.state.skip_tokens = SKIP_TOKENS;
+        game
+    }
+
+    /// A seeded game where buildings go on any empty cell next to the city.
+    pub fn with_frontier(seed: u64) -> Self {
+        Self::start(
+            seed,
+            State {
+                terrain_seed: Some(seed),
+                frontier: true,
+                ..State::initialize()
+            },
+        )
+    }
+
+    /// A seeded game with the rules asked for: steering the spiral, building
+    /// on the frontier, or neither. The two cannot be combined.
+    pub fn with_rules(seed: u64, steering: bool, frontier: bool) -> Result<Self, String> {
+        match (steering, frontier) {
+            (true, true) => Err(String::from(
+                "steering and frontier games cannot be combined",
+            )),
+            (true, false) => Ok(Self::with_steering(seed)),
+            (false, true) => Ok(Self::with_frontier(seed)),
+            (false, false) => Ok(Self::new(seed)),
+        }
+    }
+
+    /// Give this game the unlocks of `profile`, drawing the first offer
+    /// again since more buildings may be offered.
+    pub fn with_profile(mut self, profile: &Profile) -> Self {
+        profile.apply(&mut self.state);
+        self.offer = self.state.propose_buildings(&mut self.rng);
+        self
+    }
+
+    /// Draft perks and draw the first offer for `state` from the seeded
+    /// generator.
+    fn start(seed: u64, mut state: State) -> Self {
+        state.perk_draft = Perk::draft(seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let offer = state.propose_buildings(&mut rng);
+        Self {
+            seed,
+            state,
+            offer,
+            history: vec![],
+            rng,
+        }
+    }
+
+    /// Which offered buildings can be built, none once the run has ended on
+    /// an unpaid tribute.
+    pub fn legal_moves(&self) -> Vec<bool> {
+        if self.state.tribute_failed {
+            return vec![false; self.offer.len()];
+        }
+        self.offer
+            .iter()
+            .map(|building| building.can_be_built(&self.state))
+            .collect()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.state.tribute_failed
+            || (self.legal_moves().iter().all(|legal| !legal)
+                && self.state.upgradable_cells().is_empty()
+                && !self.state.can_trade()
+                && self.state.skip_tokens == 0
+                && self.state.rerolls == 0)
+    }
+
+    /// Build the offered building at `index` and draw the next offer.
+    pub fn choose(&mut self, index: usize) -> Result<Building, String> {
+        self.play(Action::Build { index })?;
+        Ok(self.history.last().unwrap().built)
+    }
+
+    pub fn play(&mut self, action: Action) -> Result<(), String> {
+        if self.state.tribute_failed {
+            return Err(String::from("the tribute was not paid, the game is over"));
+        }
+        let rings = self.state.rings;
+        let (index, target) = match action {
+            Action::Build { index } => (index, None),
+            Action::Rebuild { index, x, y } | Action::BuildAt { index, x, y } => {
+                (index, Some((x, y)))
+            }
+            Action::Demolish { x, y } => {
+                self.state = self.state.demolish((x, y))?;
+                return Ok(());
+            }
+            Action::Prioritise { building } => {
+                self.state = self.state.prioritise(building)?;
+                return Ok(());
+            }
+            Action::Trade { give, get, amount } => {
+                self.state = self.state.trade(give, get, amount)?;
+                return Ok(());
+            }
+            Action::Steer => {
+                self.state = self.state.steer()?;
+                return Ok(());
+            }
+            Action::Skip => {
+                self.state = self.state.skip()?;
+                return Ok(());
+            }
+            Action::Accept => {
+                self.state = self.state.accept_contract()?;
+                return Ok(());
+            }
+            Action::Reroll => {
+                self.state = self.state.reroll()?;
+                self.offer = self.state.propose_buildings(&mut self.rng);
+                return Ok(());
+            }
+            Action::PickPerk { index } => {
+                // The perk can change what is offered
+                self.state = self.state.pick_perk(index)?;
+                self.offer = self.state.propose_buildings(&mut self.rng);
+                return Ok(());
+            }
+            Action::Upgrade { x, y } => {
+                self.state = self.state.upgrade((x, y))?;
+                let building = self.state.spiral[&(x, y)];
+                self.end_turn(building, (x, y), None);
+                return Ok(());
+            }
+        };
+
+        let Some(&building) = self.offer.get(index) else {
+            return Err(format!("no building at index {index} in the offer"));
+        };
+        if !self.state.is_free(building)
+            && !self
+                .state
+                .owned_resources
+                .can_afford(&perks::cost(&self.state, building))
+        {
+            return Err(format!(
+                "{} cannot be built with the current resources",
+                building.building_to_string()
+            ));
+        }
+        let position = match (action, target) {
+            (Action::BuildAt { .. }, Some(coordinates)) => {
+                self.state = self.state.build_at(building, coordinates)?;
+                coordinates
+            }
+            (_, Some(coordinates)) => {
+                self.state = self.state.rebuild(building, coordinates)?;
+                coordinates
+            }
+            (_, None) => {
+                let Some(coordinates) = self.state.target_for(building) else {
+                    if self.state.frontier {
+                        return Err(format!(
+                            "there is no frontier cell where {} can be built",
+                            building.building_to_string()
+                        ));
+                    }
+                    let terrain = self.state.terrain(self.state.get_next_position());
+                    return Err(format!(
+                        "{} cannot be built on {terrain:?}",
+                        building.building_to_string()
+                    ));
+                };
+                if self.state.frontier {
+                    self.state = self.state.build_at(building, coordinates)?;
+                } else {
+                    self.state = self.state.turn(building);
+                }
+                coordinates
+            }
+        };
+        let ring = (self.state.rings > rings).then_some(self.state.rings);
+        self.end_turn(building, position, ring);
+        Ok(())
+    }
+
+    /// Record the turn that was just played, roll for a random event, offer
+    /// a contract every few turns and draw the next offer.
+    fn end_turn(&mut self, built: Building, position: (i32, i32), ring: Option<u32>) {
+        let event = Event::roll(&self.state, &mut self.rng);
+        self.history.push(TurnRecord {
+            turn: self.state.turn,
+            offer: self.offer.clone(),
+            built,
+            level: self.state.level(position),
+            position,
+            delta_production: self.state.delta_production.clone(),
+            lost_production: self.state.lost_production.clone(),
+            event: event.clone(),
+            ring,
+            rewards: ring.map(milestones::rewards).unwrap_or_default(),
+            contracts: self.state.settled_contracts.clone(),
+        });
+        if let Some(event) = event {
+            event.apply(&mut self.state);
+            self.state.events.push((self.state.turn, event));
+        }
+        if self.state.turn.is_multiple_of(CONTRACT_INTERVAL) {
+            self.state.contract_offer = Some(Contract::roll(&self.state, &mut self.rng));
+        }
+        self.offer = self.state.propose_buildings(&mut self.rng);
+    }
+}
+
+#[test]
+fn test_parse_action() {
+    assert_eq!(Action::parse("2"), Some(Action::Build { index: 1 }));
+    assert_eq!(
+        Action::parse("d -1 2"),
+        Some(Action::Demolish { x: -1, y: 2 })
+    );
+    assert_eq!(
+        Action::parse("R 1 0 -1"),
+        Some(Action::Rebuild {
+            index: 0,
+            x: 0,
+            y: -1
+        })
+    );
+    assert_eq!(Action::parse("u 1 0"), Some(Action::Upgrade { x: 1, y: 0 }));
+    assert_eq!(
+        Action::parse("p Quarry"),
+        Some(Action::Prioritise {
+            building: Building::Quarry
+        })
+    );
+    assert_eq!(Action::parse("P castle"), None);
+    assert_eq!(
+        Action::parse("t Wood food 3"),
+        Some(Action::Trade {
+            give: Good::Wood,
+            get: Good::Food,
+            amount: 3
+        })
+    );
+    assert_eq!(
+        Action::parse("B 2 -1 0"),
+        Some(Action::BuildAt {
+            index: 1,
+            x: -1,
+            y: 0
+        })
+    );
+    assert_eq!(Action::parse("w"), Some(Action::Steer));
+    assert_eq!(Action::parse("S"), Some(Action::Skip));
+    assert_eq!(Action::parse("a"), Some(Action::Accept));
+    assert_eq!(Action::parse("N"), Some(Action::Reroll));
+    assert_eq!(Action::parse("0"), None);
+    assert_eq!(Action::parse("D 1"), None);
+}
@@ -0,0 +1,278 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::game::{Action, Game};
+use crate::protocol::StateView;
+use crate::sessions::SharedSessions;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 64 * 1024;
+
+/// Minimal HTTP/1.1 service exposing the engine as JSON:
+/// - `POST /games` with `{"seed": 42, "steering": true}` (all optional)
+///   creates a game, `"frontier": true` for a frontier game
+/// - `GET /games/{id}` returns its state, offer and legal moves
+/// - `GET /games/{id}/offer` returns the current offer only
+/// - `POST /games/{id}/choice` with `{"index": 0}` plays a turn
+/// - `POST /games/{id}/action` plays any `Action`, e.g.
+///   `{"type": "demolish", "x": 1, "y": 0}`
+/// - `GET /games/{id}/history` returns every turn played so far
+/// - `DELETE /games/{id}` closes the game
+pub fn serve(listener: TcpListener, sessions: SharedSessions) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let sessions = sessions.clone();
+        thread::spawn(move || {
+            let _ = handle_connection(stream, sessions);
+        });
+    }
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct CreateGame {
+    seed: Option<u64>,
+    #[serde(default)]
+    steering: bool,
+    #[serde(default)]
+    frontier: bool,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    index: usize,
+}
+
+fn handle_connection(stream: TcpStream, sessions: SharedSessions) -> io::Result<()> {
+    let mut output = stream.try_clone()?;
+    let response = match read_request(&mut BufReader::new(stream))? {
+        Ok(request) => route(&request, &sessions),
+        Err(response) => response,
+    };
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Unprocessable Entity",
+    };
+    let body = response.body.to_string();
+    write!(
+        output,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        response.status,
+        body.len()
+    )?;
+    output.flush()
+}
+
+/// Read a request, or the error response to send instead when it is
+/// malformed or its body is too large.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Result<Request, Response>> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(Err(Response::error(400, "malformed request")));
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    if content_length > MAX_BODY {
+        return Ok(Err(Response::error(413, "request body too large")));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }))
+}
+
+fn game_summary(id: u32, game: &Game) -> Value {
+    json!({
+        "id": id,
+        "seed": game.seed,
+        "state": StateView::from_state(&game.state),
+        "offer": game.offer,
+        "legal_moves": game.legal_moves(),
+        "over": game.is_over(),
+    })
+}
+
+fn route(request: &Request, sessions: &SharedSessions) -> Response {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let mut sessions = sessions.lock().unwrap();
+
+    if segments == ["games"] {
+        if request.method != "POST" {
+            return Response::error(405, "use POST to create a game");
+        }
+        let settings: CreateGame = if request.body.trim().is_empty() {
+            CreateGame::default()
+        } else {
+            match serde_json::from_str(&request.body) {
+                Ok(settings) => settings,
+                Err(e) => return Response::error(400, &e.to_string()),
+            }
+        };
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        let id = match sessions.create(seed, settings.steering, settings.frontier) {
+            Ok(id) => id,
+            Err(message) => return Response::error(422, &message),
+        };
+        let mut response = Response::ok(game_summary(id, sessions.get(id).unwrap()));
+        response.status = 201;
+        return response;
+    }
+
+    let (Some(&"games"), Some(Ok(id))) = (segments.first(), segments.get(1).map(|s| s.parse()))
+    else {
+        return Response::error(404, "unknown endpoint");
+    };
+    if request.method == "DELETE" && segments.len() == 2 {
+        return match sessions.close(id) {
+            true => Response::ok(json!({ "id": id, "closed": true })),
+            false => Response::error(404, "no such game"),
+        };
+    }
+    let Some(game) = sessions.get_mut(id) else {
+        return Response::error(404, "no such game");
+    };
+
+    match (request.method.as_str(), &segments[2..]) {
+        ("GET", []) => Response::ok(game_summary(id, game)),
+        ("GET", ["offer"]) => Response::ok(json!({
+            "offer": game.offer,
+            "legal_moves": game.legal_moves(),
+        })),
+        ("GET", ["history"]) => Response::ok(json!({ "history": game.history })),
+        ("POST", ["choice"]) => {
+            let choice: Choice = match serde_json::from_str(&request.body) {
+                Ok(choice) => choice,
+                Err(e) => return Response::error(400, &e.to_string()),
+            };
+            match game.choose(choice.index) {
+                Ok(_) => Response::ok(json!({
+                    "turn": game.history.last(),
+                    "game": game_summary(id, game),
+                })),
+                Err(message) => Response::error(422, &message),
+            }
+        }
+        ("POST", ["action"]) => {
+            let action: Action = match serde_json::from_str(&request.body) {
+                Ok(action) => action,
+                Err(e) => return Response::error(400, &e.to_string()),
+            };
+            match game.play(action) {
+                Ok(()) => Response::ok(game_summary(id, game)),
+                Err(message) => Response::error(422, &message),
+            }
+        }
+        _ => Response::error(404, "unknown endpoint"),
+    }
+}
+
+#[test]
+fn test_http_api() {
+    use crate::sessions::Sessions;
+    use std::io::Read;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener, Sessions::shared()));
+
+    let request = |method: &str, path: &str, body: &str| -> (u16, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    };
+
+    let (status, created) = request("POST", "/games", r#"{"seed": 3}"#);
+    assert_eq!(status, 201);
+    let id = created["id"].as_u64().unwrap();
+    let legal = created["legal_moves"].as_array().unwrap();
+    let index = legal.iter().position(|legal| legal == true).unwrap();
+
+    let (status, played) = request(
+        "POST",
+        &format!("/games/{id}/choice"),
+        &format!(r#"{{"index": {index}}}"#),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(played["game"]["state"]["turn"], 1);
+
+    let (status, steered) = request("POST", "/games", r#"{"seed": 3, "steering": true}"#);
+    assert_eq!(status, 201);
+    assert_eq!(steered["state"]["steering"], true);
+    let both = r#"{"steering": true, "frontier": true}"#;
+    assert_eq!(request("POST", "/games", both).0, 422);
+
+    let (_, history) = request("GET", &format!("/games/{id}/history"), "");
+    assert_eq!(history["history"].as_array().unwrap().len(), 1);
+    assert_eq!(request("GET", "/games/999", "").0, 404);
+
+    // A body announced larger than the limit is refused before it is read
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /games HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+        MAX_BODY + 1
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+}
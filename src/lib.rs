@@ -0,0 +1,19 @@
+pub mod buildings;
+pub mod contracts;
+pub mod districts;
+pub mod env;
+pub mod events;
+pub mod ffi;
+pub mod fields;
+pub mod game;
+pub mod http;
+pub mod milestones;
+pub mod perks;
+pub mod profiles;
+pub mod protocol;
+pub mod resources;
+pub mod server;
+pub mod sessions;
+pub mod states;
+pub mod terrain;
+pub mod tributes;
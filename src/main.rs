@@ -1,17 +1,18 @@
 use std::collections::HashMap;
+use std::net::TcpListener;
+use std::{env, io, process};
 
-use crate::buildings::Building;
-pub mod buildings;
+use spiral_city::buildings::Building;
+use spiral_city::game::{Action, Game};
+use spiral_city::http;
+use spiral_city::profiles::{DEFAULT_PROFILE_PATH, Profile, Unlock};
+use spiral_city::protocol;
+use spiral_city::resources::GlobalResources;
+use spiral_city::server;
+use spiral_city::sessions::Sessions;
+use spiral_city::states::{Direction, State};
 
-use crate::resources::GlobalResources;
-pub mod resources;
-
-use crate::states::State;
-pub mod states;
-
-use crate::states::Direction;
-
-fn play() {
+fn play(game: &mut Game) {
     print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
     println!("---------------------------");
     println!("Welcome to 🌀 SpiralCity 🌀");
@@ -19,14 +20,17 @@ fn play() {
     println!(
         "Your goal is to go as far as possible in the spiral, by choosing the good next building."
     );
-    println!("You loose if you cannot build any of the 2 proposed buildings.");
+    println!(
+        "You loose if you cannot build any of the 2 proposed buildings, or cannot pay a tribute 👑 when it is due."
+    );
     println!("Have fun!");
-    let mut state = State::initialize();
     loop {
-        let option_state = state.turn();
-        if let Some(st) = option_state {
-            state = st;
-            print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
+        game.state.print();
+        if let Some(action) = game.state.choose_building(&game.offer) {
+            match game.play(action) {
+                Ok(()) => print!("{esc}[2J{esc}[1;1H", esc = 27 as char),
+                Err(message) => println!("You cannot do that: {message}"),
+            }
         } else {
             println!("Thanks for playing!");
             break;
@@ -51,6 +55,7 @@ fn test_spiral_print() {
         occupied_people: 3,
         wood: 8,
         rock: 4,
+        ..GlobalResources::initialize()
     };
     let example = State {
         turn: 6,
@@ -61,11 +66,12 @@ fn test_spiral_print() {
         current_position: (-1, 1),
         direction: Direction::Right,
         delta_production: vec![],
+        ..State::initialize()
     };
     let res = example.spiral_to_string();
     assert_eq!(
         res,
-        String::from("⬛⬛⬛⬛⬛\n⬛🏠🟪⬛⬛\n⬛🪚🏠🌲⬛\n⬛🪨🏠🏠⬛\n⬛⬛⬛⬛⬛\n")
+        String::from("⬛⬛⬛⬛⬛\n⬛🏠🡺 ⬛⬛\n⬛🪚🏠🌲⬛\n⬛🪨🏠🏠⬛\n⬛⬛⬛⬛⬛\n")
     );
 }
 
@@ -91,6 +97,548 @@ fn test_get_next_position() {
     assert_eq!(example_coordinates.get_next_position(), (-1, 1));
 }
 
+#[test]
+fn test_unpaid_upkeep() {
+    // A workshop without wood for its upkeep stops boosting its neighbours
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.spiral.insert((1, -1), Building::Workshop);
+    example.current_position = (1, -1);
+    example.direction = Direction::Left;
+    example.x_bounds = (0, 1);
+    example.y_bounds = (-1, 0);
+    // Enough people to staff the farm, the forest and the workshop
+    for coordinates in [(-1, 0), (-1, 1), (0, 1)] {
+        example.spiral.insert(coordinates, Building::House);
+    }
+    example.owned_resources.total_people = 4;
+
+    let next = example.turn(Building::Farm);
+    assert!(next.inactive.contains(&(1, -1)));
+    assert_eq!(next.owned_resources.wood, 2);
+    assert!(next.spiral_to_string().contains('💤'));
+
+    example.owned_resources.wood = 1;
+    let next = example.turn(Building::Farm);
+    assert!(next.inactive.is_empty());
+    assert_eq!(next.owned_resources.wood, 3);
+}
+
+#[test]
+fn test_worker_priorities() {
+    // One person for a forest and a quarry: only the first in the staffing
+    // order produces
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.spiral.insert((1, -1), Building::Quarry);
+    example.current_position = (1, -1);
+    example.direction = Direction::Left;
+    example.x_bounds = (0, 1);
+    example.y_bounds = (-1, 0);
+    example.owned_resources.wood = 2;
+    example.owned_resources.rock = 1;
+
+    let next = example.turn(Building::Warehouse);
+    assert_eq!(next.workers_on((1, 0)), 1);
+    assert_eq!(next.workers_on((1, -1)), 0);
+    assert_eq!(next.understaffed_cells(), vec![(1, -1)]);
+    assert_eq!(
+        (next.owned_resources.wood, next.owned_resources.rock),
+        (1, 0)
+    );
+
+    let example = example.prioritise(Building::Quarry).unwrap();
+    assert_eq!(example.priorities[0], Building::Quarry);
+    let next = example.turn(Building::Warehouse);
+    assert_eq!(
+        (next.owned_resources.wood, next.owned_resources.rock),
+        (0, 1)
+    );
+    assert!(example.prioritise(Building::House).is_err());
+}
+
+#[test]
+fn test_storage() {
+    use spiral_city::resources::{BASE_STORAGE, Resources};
+
+    // A quarry with a full store: its rock is lost, and the turn's changes
+    // show it coming in then going out
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.spiral.insert((1, -1), Building::Quarry);
+    example.current_position = (1, -1);
+    example.direction = Direction::Left;
+    example.x_bounds = (0, 1);
+    example.y_bounds = (-1, 0);
+    example.owned_resources.wood = 2;
+    example.owned_resources.rock = BASE_STORAGE;
+    let example = example.prioritise(Building::Quarry).unwrap();
+
+    let next = example.turn(Building::House);
+    assert_eq!(next.owned_resources.rock, BASE_STORAGE);
+    assert_eq!(next.lost_production, vec![Resources::Rock(1)]);
+    let rock: Vec<&Resources> = next
+        .delta_production
+        .iter()
+        .filter(|resource| matches!(resource, Resources::Rock(_)))
+        .collect();
+    assert_eq!(rock, vec![&Resources::Rock(1), &Resources::Rock(-1)]);
+
+    // A warehouse next to the quarry stores 10 more of each good and 5 more
+    // rock
+    let next = example.turn(Building::Warehouse);
+    assert_eq!(
+        Building::Warehouse.storage((0, -1), &next),
+        vec![
+            Resources::Wood(10),
+            Resources::Rock(15),
+            Resources::Food(10)
+        ]
+    );
+    assert_eq!(next.owned_resources.wood_cap, BASE_STORAGE + 10);
+    assert_eq!(next.owned_resources.rock_cap, BASE_STORAGE + 15);
+    assert!(next.lost_production.is_empty());
+}
+
+#[test]
+fn test_partly_staffed_workshop() {
+    use spiral_city::resources::Resources;
+
+    // A workshop with one of its two workers gives half its boost, rounded
+    // up so that the worker always counts
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.spiral.insert((2, 0), Building::Workshop);
+    example.workers.insert((1, 0), 1);
+    let wood = |state: &State| match Building::Forest.production((1, 0), state) {
+        Some(Resources::Wood(n)) => n,
+        other => panic!("unexpected production {other:?}"),
+    };
+    let unstaffed = wood(&example);
+
+    example.workers.insert((2, 0), 1);
+    assert_eq!(wood(&example), unstaffed + 1);
+    example.levels.insert((2, 0), 2);
+    assert_eq!(wood(&example), unstaffed + 1);
+    example.levels.insert((2, 0), 3);
+    assert_eq!(wood(&example), unstaffed + 2);
+    example.workers.insert((2, 0), 2);
+    assert_eq!(wood(&example), unstaffed + 3);
+}
+
+#[test]
+fn test_first_offer_is_playable() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    // Whatever the seed, something offered on the first turn can be built
+    for seed in 0..1000 {
+        assert!(!Game::new(seed).is_over(), "seed {seed}");
+    }
+
+    // Warehouses are offered from the start but cost wood and rock, an
+    // empty stock still leaves something to build next to them
+    let mut example = State::initialize();
+    example.owned_resources.food = 0;
+    let mut rng = StdRng::seed_from_u64(0);
+    let offers: Vec<Vec<Building>> = (0..500)
+        .map(|_| example.propose_buildings(&mut rng))
+        .collect();
+    assert!(offers.iter().flatten().any(|&b| b == Building::Warehouse));
+    for offer in &offers {
+        assert!(offer.iter().any(|building| building.can_be_built(&example)));
+    }
+
+    // So does a market unlocked from the start by the profile, without
+    // counting on a perk to pay for it
+    let profile = Profile {
+        unlocks: vec![Unlock::Building(Building::Market)],
+        ..Profile::default()
+    };
+    let games: Vec<Game> = (0..1000)
+        .map(|seed| Game::new(seed).with_profile(&profile))
+        .collect();
+    assert!(
+        games
+            .iter()
+            .any(|game| game.offer.contains(&Building::Market))
+    );
+    for game in &games {
+        assert!(!game.is_over(), "seed {}", game.seed);
+    }
+}
+
+#[test]
+fn test_event_durations() {
+    use spiral_city::events::Event;
+
+    // Turns on which a lone producer makes a different amount with the
+    // event than without
+    let affected_turns = |producer: Building, event: Event| {
+        let mut calm = State::initialize().turn(producer);
+        calm.owned_resources.wood = 10;
+        let mut eventful = calm.clone();
+        event.apply(&mut eventful);
+        let mut affected = 0;
+        for _ in 0..5 {
+            let next_calm = calm.turn(Building::House);
+            let next_eventful = eventful.turn(Building::House);
+            if next_calm.delta_production != next_eventful.delta_production {
+                affected += 1;
+            }
+            (calm, eventful) = (next_calm, next_eventful);
+        }
+        affected
+    };
+    assert_eq!(
+        affected_turns(Building::Forest, Event::Storm { turns: 3 }),
+        3
+    );
+    assert_eq!(
+        affected_turns(Building::Quarry, Event::MiningBoom { turns: 3 }),
+        3
+    );
+}
+
+#[test]
+fn test_population_growth() {
+    let mut example = State::initialize();
+    example.owned_resources.wood = 1;
+    example.owned_resources.food = 4;
+    // Two houses, one next to a forest: 25 + 25 (food in stock) + 10
+    let next = example.turn(Building::House);
+    assert_eq!(next.owned_resources.housing, 4);
+    assert_eq!(next.owned_resources.total_people, 1);
+    assert_eq!(next.growth_progress, 50);
+    let next = next.turn(Building::Forest);
+    assert_eq!(next.growth_progress, 10);
+    assert_eq!(next.owned_resources.total_people, 2);
+    // Not enough food in stock for both people any more
+    assert_eq!(next.growth_rate(), 35);
+
+    // Hungry people leave, and nobody stays without a house
+    let mut hungry = next.clone();
+    hungry.owned_resources.hungry_people = 1;
+    hungry.owned_resources.food = 1;
+    assert_eq!(hungry.growth_rate(), 0);
+    let hungry = hungry.turn(Building::Farm);
+    assert_eq!(hungry.owned_resources.total_people, 1);
+    let mut crowded = next.clone();
+    crowded.owned_resources.total_people = 4;
+    let evicted = crowded.demolish((0, 0)).unwrap();
+    assert_eq!(evicted.owned_resources.housing, 2);
+    assert_eq!(evicted.owned_resources.total_people, 2);
+}
+
+#[test]
+fn test_market_trade() {
+    use spiral_city::resources::Good;
+
+    let mut example = State::initialize();
+    example.owned_resources.wood = 10;
+    assert!(example.trade(Good::Wood, Good::Rock, 1).is_err());
+
+    example.spiral.insert((1, 0), Building::Market);
+    example.current_position = (1, 0);
+    example.direction = Direction::Down;
+    example.x_bounds = (0, 1);
+    assert_eq!(example.trade_price(Good::Rock), Some(3));
+    // Each unit bought raises the price: 3 + 4 wood for 2 rocks
+    let traded = example.trade(Good::Wood, Good::Rock, 2).unwrap();
+    assert_eq!(traded.owned_resources.wood, 3);
+    assert_eq!(traded.owned_resources.rock, 2);
+    assert_eq!(traded.trade_price(Good::Rock), Some(5));
+    assert_eq!(traded.trade_price(Good::Food), Some(3));
+    assert!(traded.trade(Good::Wood, Good::Rock, 1).is_err());
+    assert!(traded.trade(Good::Rock, Good::Rock, 1).is_err());
+
+    // Prices come back down over the turns
+    let next = traded.turn(Building::Farm);
+    assert_eq!(next.trade_price(Good::Rock), Some(4));
+}
+
+#[test]
+fn test_demolish_and_rebuild() {
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.current_position = (1, 0);
+    example.direction = Direction::Down;
+    example.x_bounds = (0, 1);
+    example.owned_resources.occupied_people = 1;
+    example.owned_resources.wood = 3;
+
+    let demolished = example.demolish((1, 0)).unwrap();
+    assert!(demolished.rubble.contains(&(1, 0)));
+    assert_eq!(demolished.owned_resources.occupied_people, 0);
+    assert_eq!(demolished.owned_resources.wood, 2);
+    assert!(demolished.spiral_to_string().contains('🧱'));
+    assert!(demolished.demolish((1, 0)).is_err());
+
+    let rebuilt = demolished.rebuild(Building::Quarry, (1, 0)).unwrap();
+    assert_eq!(rebuilt.spiral.get(&(1, 0)), Some(&Building::Quarry));
+    assert!(rebuilt.rubble.is_empty());
+    assert_eq!(rebuilt.current_position, (1, 0));
+    assert!(rebuilt.rebuild(Building::Quarry, (2, 0)).is_err());
+}
+
+#[test]
+fn test_steering_and_skipping() {
+    use spiral_city::states::Winding;
+
+    let mut example = State::initialize();
+    example.owned_resources.wood = 5;
+    assert!(example.steer().is_err());
+    assert!(example.skip().is_err());
+
+    example.steering = true;
+    example.skip_tokens = 1;
+    // (1, 0) is a corner, counter-clockwise the spiral goes up after it
+    let steered = example.steer().unwrap();
+    assert_eq!(steered.winding, Winding::CounterClockwise);
+    assert!(steered.spiral_to_string().contains('🔹'));
+    let built = steered.turn(Building::House);
+    assert_eq!(built.get_next_position(), (1, 1));
+    assert!(built.at_corner());
+
+    let skipped = built.skip().unwrap();
+    assert!(skipped.skipped.contains(&(1, 1)));
+    assert_eq!(skipped.get_next_position(), (0, 1));
+    assert_eq!(skipped.turn, 1);
+    assert!(skipped.skip().is_err());
+
+    let rebuilt = skipped.rebuild(Building::House, (1, 1)).unwrap();
+    assert!(rebuilt.skipped.is_empty());
+    assert_eq!(rebuilt.spiral.get(&(1, 1)), Some(&Building::House));
+    assert_eq!(rebuilt.get_next_position(), (0, 1));
+
+    // Steering at the next corner turns away from (0, -1), which is left
+    // behind as a skipped cell, and given back when steering back
+    let built = example.turn(Building::House);
+    assert_eq!(built.get_next_position(), (1, -1));
+    let steered = built.steer().unwrap();
+    assert!(steered.skipped.contains(&(0, -1)));
+    assert!(steered.steer().unwrap().skipped.is_empty());
+
+    let moved_on = steered.turn(Building::House);
+    assert_eq!(moved_on.get_next_position(), (2, -1));
+    let rebuilt = moved_on.rebuild(Building::House, (0, -1)).unwrap();
+    assert!(rebuilt.skipped.is_empty());
+    assert_eq!(rebuilt.spiral.get(&(0, -1)), Some(&Building::House));
+}
+
+#[test]
+fn test_frontier() {
+    let mut example = State::initialize();
+    example.owned_resources.wood = 5;
+    assert!(example.build_at(Building::House, (0, 1)).is_err());
+
+    example.frontier = true;
+    assert_eq!(
+        example.frontier_cells(),
+        vec![(-1, 0), (0, -1), (0, 1), (1, 0)]
+    );
+    assert!(example.build_at(Building::House, (0, 2)).is_err());
+    let built = example.build_at(Building::House, (0, 1)).unwrap();
+    assert_eq!(built.spiral.get(&(0, 1)), Some(&Building::House));
+    assert_eq!(built.y_bounds, (0, 1));
+    assert_eq!(built.current_position, (0, 1));
+    // The nearest frontier cell to the last building comes first
+    assert_eq!(built.target_for(Building::Farm), Some((-1, 1)));
+    assert!(built.spiral_to_string().contains('➕'));
+}
+
+#[test]
+fn test_wonder() {
+    let mut example = State::initialize();
+    example.owned_resources.wood = 8;
+    example.owned_resources.rock = 12;
+    example.free_buildings = 1;
+    assert!(!example.is_free(Building::Colossus));
+    assert!(Building::Colossus.can_be_built(&example));
+
+    let built = example.turn(Building::Colossus);
+    assert_eq!(
+        built.wonder_cells(Building::Colossus),
+        vec![(1, 0), (1, -1)]
+    );
+    assert_eq!(built.spiral.get(&(1, -1)), Some(&Building::Colossus));
+    assert_eq!(built.current_position, (1, -1));
+    assert_eq!(built.owned_resources.wood, 0);
+    assert_eq!(built.free_buildings, 1);
+    assert!(built.spiral_to_string().contains('🟪'));
+    assert!(built.check_upgrade((1, 0)).is_err());
+    assert!(built.target_for(Building::Colossus).is_none());
+
+    let mut demolished = built.clone();
+    demolished.destroy((1, -1));
+    assert!(demolished.wonders.is_empty());
+    assert!(demolished.rubble.contains(&(1, 0)) && demolished.rubble.contains(&(1, -1)));
+}
+
+#[test]
+fn test_contracts() {
+    use spiral_city::contracts::{Contract, Goal};
+    use spiral_city::resources::{Good, Resources};
+
+    let mut example = State::initialize();
+    example.owned_resources.wood = 20;
+    let failed = Contract {
+        goal: Goal::Rings { rings: 1 },
+        deadline: 1,
+        reward: vec![Resources::Wood(5)],
+        penalty: vec![Resources::Food(10)],
+    };
+    example.contract_offer = Some(failed.clone());
+    let accepted = example.accept_contract().unwrap();
+    assert!(accepted.contract_offer.is_none());
+    assert!(accepted.accept_contract().is_err());
+
+    // The deadline turn still counts, the penalty comes after it
+    let first = accepted.turn(Building::House);
+    assert_eq!(first.contracts, vec![failed.clone()]);
+    let mut second = first.turn(Building::House);
+    assert!(second.contracts.is_empty());
+    assert_eq!(second.settled_contracts, vec![(failed, false)]);
+    assert_eq!(second.owned_resources.food, 0);
+
+    let met = Contract {
+        goal: Goal::Bank {
+            good: Good::Wood,
+            amount: 1,
+        },
+        deadline: 10,
+        reward: vec![Resources::Rock(5)],
+        penalty: vec![],
+    };
+    second.contracts.push(met.clone());
+    let third = second.turn(Building::House);
+    assert_eq!(third.settled_contracts, vec![(met, true)]);
+    assert_eq!(third.owned_resources.rock, 5);
+}
+
+#[test]
+fn test_tribute() {
+    use spiral_city::tributes::Tribute;
+
+    let mut example = State::initialize();
+    example.turn = 9;
+    example.owned_resources.wood = 20;
+    example.owned_resources.rock = 5;
+    let paid = example.turn(Building::House);
+    assert!(!paid.tribute_failed);
+    assert_eq!(paid.owned_resources.wood, 20 - 1 - 10);
+    assert_eq!(paid.owned_resources.rock, 0);
+    assert_eq!(paid.tribute, Tribute::after(10));
+
+    example.owned_resources.rock = 4;
+    let failed = example.turn(Building::House);
+    assert!(failed.tribute_failed);
+    assert_eq!(failed.tribute.due, 10);
+    assert_eq!(failed.owned_resources.rock, 4);
+}
+
+#[test]
+fn test_rerolls() {
+    use spiral_city::profiles::{Profile, Unlock};
+
+    let profile = Profile {
+        unlocks: vec![Unlock::StartingWood, Unlock::Reroll],
+        ..Profile::default()
+    };
+    let mut game = Game::new(3).with_profile(&profile);
+    assert_eq!(game.state.owned_resources.wood, 5);
+    assert_eq!(game.state.rerolls, 1);
+    game.play(Action::Reroll).unwrap();
+    assert_eq!(game.state.rerolls, 0);
+    assert_eq!(game.state.turn, 0);
+    assert!(game.play(Action::Reroll).is_err());
+}
+
+#[test]
+fn test_perk_draft() {
+    use spiral_city::perks::Perk;
+
+    let mut game = Game::new(3);
+    assert_eq!(
+        game.state.perk_draft,
+        vec![Perk::WideOffers, Perk::Harmony, Perk::HeadStart]
+    );
+    assert!(game.play(Action::PickPerk { index: 3 }).is_err());
+    game.play(Action::PickPerk { index: 0 }).unwrap();
+    assert_eq!(game.state.perks, vec![Perk::WideOffers]);
+    assert!(game.state.perk_draft.is_empty());
+    assert_eq!(game.offer.len(), 3);
+    assert!(game.play(Action::PickPerk { index: 0 }).is_err());
+
+    // The draft closes with the first turn
+    let mut game = Game::new(2);
+    let index = game.legal_moves().iter().position(|&legal| legal).unwrap();
+    game.choose(index).unwrap();
+    assert!(game.play(Action::PickPerk { index: 0 }).is_err());
+
+    // Harmony removes the penalty between forests and quarries
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.spiral.insert((1, -1), Building::Quarry);
+    example.workers.insert((1, 0), 1);
+    example.workers.insert((1, -1), 1);
+    let penalised = Building::Forest.production((1, 0), &example);
+    example.perks.push(Perk::Harmony);
+    let harmony = Building::Forest.production((1, 0), &example);
+    assert_eq!(penalised, Some(spiral_city::resources::Resources::Wood(1)));
+    assert_eq!(harmony, Some(spiral_city::resources::Resources::Wood(2)));
+}
+
+#[test]
+fn test_wonder_offer_fits() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use spiral_city::terrain::Terrain;
+
+    // Only a fishery fits on a river, a wonder must not push it out
+    let mut example = State::initialize();
+    example.turn = 20;
+    example.rings = 2;
+    example.spiral.insert((1, 0), Building::Forest);
+    example.current_position = (1, 0);
+    let next = example.get_next_position();
+    example.terrain_seed = (0..).find(|&seed| Terrain::generate(seed, next) == Terrain::River);
+    let mut rng = StdRng::seed_from_u64(0);
+    let offers: Vec<Vec<Building>> = (0..300)
+        .map(|_| example.propose_buildings(&mut rng))
+        .collect();
+    for offer in &offers {
+        assert!(
+            offer
+                .iter()
+                .any(|&building| example.target_for(building).is_some())
+        );
+    }
+    assert!(offers.iter().flatten().any(|building| building.is_wonder()));
+}
+
+#[test]
+fn test_upgrade() {
+    let mut example = State::initialize();
+    example.spiral.insert((1, 0), Building::Forest);
+    example.current_position = (1, 0);
+    example.direction = Direction::Down;
+    example.x_bounds = (0, 1);
+    example.owned_resources.wood = 1;
+    example.owned_resources.rock = 2;
+    assert!(example.upgrade((1, 0)).is_err());
+
+    example.owned_resources.wood = 2;
+    let upgraded = example.upgrade((1, 0)).unwrap();
+    assert_eq!(upgraded.level((1, 0)), 2);
+    assert_eq!(upgraded.turn, 1);
+    assert_eq!(upgraded.current_position, (1, 0));
+    // A managed forest produces 3 wood instead of 2
+    assert_eq!(upgraded.owned_resources.wood, 3);
+    assert!(upgraded.spiral_to_string().contains('🌳'));
+    assert!(upgraded.upgrade((2, 0)).is_err());
+}
+
 fn _spiral_printing_example() {
     println!("--- Trying spiral printing ---");
     let mut example_spiral = HashMap::new();
@@ -107,6 +655,7 @@ fn _spiral_printing_example() {
         occupied_people: 3,
         wood: 8,
         rock: 4,
+        ..GlobalResources::initialize()
     };
     let example = State {
         turn: 6,
@@ -117,6 +666,7 @@ fn _spiral_printing_example() {
         current_position: (-1, 1),
         direction: Direction::Right,
         delta_production: vec![],
+        ..State::initialize()
     };
     example.print();
 }
@@ -137,6 +687,7 @@ fn _choose_buiding_example() {
         occupied_people: 4,
         wood: 8,
         rock: 4,
+        ..GlobalResources::initialize()
     };
     let example = State {
         turn: 6,
@@ -147,13 +698,169 @@ fn _choose_buiding_example() {
         current_position: (-1, 1),
         direction: Direction::Right,
         delta_production: vec![],
+        ..State::initialize()
     };
 
     example.print();
-    let new_building = example.choose_building();
-    println!("Chosen building: {new_building:?}");
+    let offer = example.propose_buildings(&mut rand::thread_rng());
+    let action = example.choose_building(&offer);
+    println!("Chosen action: {action:?}");
+}
+
+fn play_json(mut game: Game) {
+    protocol::play_json(&mut game, io::stdin().lock(), io::stdout().lock())
+        .expect("Expected to communicate over stdin/stdout");
+}
+
+/// Show the profile and let the player buy unlocks or reset it before a
+/// run. Returns false if the player quits instead of playing.
+fn profile_screen(profile: &mut Profile, path: &str) -> bool {
+    loop {
+        profile.print();
+        println!("> Press Enter to play, 'n' buys unlock n, 'X' resets the profile, 'Q' quits:");
+        let mut buffer = String::new();
+        io::stdin()
+            .read_line(&mut buffer)
+            .expect("Expected user input");
+        match buffer.trim() {
+            "" => return true,
+            "Q" | "q" => return false,
+            "X" | "x" => {
+                *profile = Profile::default();
+                println!("Profile reset");
+            }
+            command => {
+                let unlock = command
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| Unlock::ALL.get(n.checked_sub(1)?));
+                match unlock.map(|&unlock| profile.buy(unlock)) {
+                    Some(Ok(())) => println!("Unlocked!"),
+                    Some(Err(message)) => println!("You cannot do that: {message}"),
+                    None => println!("Please enter a correct value"),
+                }
+            }
+        }
+        profile.save(path).expect("Expected to save the profile");
+    }
+}
+
+/// Let the player pick one of the drafted perks before the first turn.
+fn perk_screen(game: &mut Game) {
+    println!("\nPick a perk for this run:");
+    for (i, perk) in game.state.perk_draft.iter().enumerate() {
+        println!("[{}] {}", i + 1, perk.describe());
+    }
+    loop {
+        let mut buffer = String::new();
+        io::stdin()
+            .read_line(&mut buffer)
+            .expect("Expected user input");
+        let picked = buffer
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|index| game.play(Action::PickPerk { index }).ok());
+        if picked.is_some() {
+            return;
+        }
+        println!(
+            "Please enter a number between 1 and {}",
+            game.state.perk_draft.len()
+        );
+    }
+}
+
+fn new_game(seed: u64, steering: bool, frontier: bool) -> Game {
+    match Game::with_rules(seed, steering, frontier) {
+        Ok(game) => game,
+        Err(message) => {
+            eprintln!("Usage error: {message}, use either --steering or --frontier");
+            process::exit(2);
+        }
+    }
+}
+
+fn run_server(port: u16) {
+    let listener =
+        TcpListener::bind(("127.0.0.1", port)).expect("Expected to listen on the given port");
+    println!("SpiralCity server listening on 127.0.0.1:{port}");
+    server::serve(listener, Sessions::shared()).expect("Expected to accept connections");
+}
+
+fn run_http(port: u16) {
+    let listener =
+        TcpListener::bind(("127.0.0.1", port)).expect("Expected to listen on the given port");
+    println!("SpiralCity HTTP API listening on http://127.0.0.1:{port}");
+    http::serve(listener, Sessions::shared()).expect("Expected to accept connections");
 }
 
 fn main() {
-    play();
+    let mut json = false;
+    let mut server_port = None;
+    let mut http_port = None;
+    let mut steering = false;
+    let mut frontier = false;
+    let mut profile_path = String::from(DEFAULT_PROFILE_PATH);
+    let mut seed = rand::random();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--protocol" => match args.next().as_deref() {
+                Some("json") => json = true,
+                Some("text") => json = false,
+                _ => panic!("--protocol expects 'json' or 'text'"),
+            },
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .expect("--seed expects a number")
+            }
+            "--steering" => steering = true,
+            "--frontier" => frontier = true,
+            "--profile" => profile_path = args.next().expect("--profile expects a file path"),
+            "--server" => {
+                server_port = Some(
+                    args.next()
+                        .and_then(|s| s.parse().ok())
+                        .expect("--server expects a port number"),
+                )
+            }
+            "--http" => {
+                http_port = Some(
+                    args.next()
+                        .and_then(|s| s.parse().ok())
+                        .expect("--http expects a port number"),
+                )
+            }
+            _ => panic!("Unknown argument: {arg}"),
+        }
+    }
+
+    if let Some(port) = http_port {
+        run_http(port);
+    } else if let Some(port) = server_port {
+        run_server(port);
+    } else if json {
+        play_json(new_game(seed, steering, frontier));
+    } else {
+        // Refuse bad options before showing the profile
+        let game = new_game(seed, steering, frontier);
+        let mut profile = Profile::load(&profile_path).expect("Expected a readable profile");
+        if !profile_screen(&mut profile, &profile_path) {
+            return;
+        }
+        let mut game = game.with_profile(&profile);
+        perk_screen(&mut game);
+        play(&mut game);
+        if game.is_over() {
+            let earned = profile.record(&game.state);
+            println!("⭐ This run earned {earned} star(s), see you next run!");
+            profile
+                .save(&profile_path)
+                .expect("Expected to save the profile");
+        }
+    }
 }
@@ -0,0 +1,119 @@
+//! Rewards for closing rings of the spiral. Ring n is complete once every
+//! cell of the (2n+1)×(2n+1) square around the first house has been built
+//! on, which a spiral that is never steered nor skipped does when it reaches
+//! (n, n).
+
+use serde::Serialize;
+
+use crate::buildings::Building;
+use crate::states::State;
+
+/// Rings with rewards of their own, the following ones only give a free
+/// building.
+const SPECIAL_RINGS: u32 = 3;
+
+/// Permanent bonuses earned by completing rings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Modifier {
+    /// Ten more storage for every good.
+    Granaries,
+    /// Farms and fisheries produce one more food.
+    Harvest,
+}
+
+impl Modifier {
+    pub fn describe(&self) -> &'static str {
+        match *self {
+            Modifier::Granaries => "🧺 Granaries: +10 storage for every good",
+            Modifier::Harvest => "🌻 Harvest: farms and fisheries +1 food 🍞",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Reward {
+    /// The next building placed costs nothing.
+    FreeBuilding,
+    /// This kind of building starts appearing in offers.
+    Unlock(Building),
+    /// A bonus for the rest of the game.
+    Modifier(Modifier),
+}
+
+impl Reward {
+    pub fn describe(&self) -> String {
+        match self {
+            Reward::FreeBuilding => "🎁 Your next building is free".to_string(),
+            Reward::Unlock(building) => format!("🔓 {} unlocked", building.building_to_string()),
+            Reward::Modifier(modifier) => modifier.describe().to_string(),
+        }
+    }
+
+    pub fn apply(&self, state: &mut State) {
+        match self {
+            Reward::FreeBuilding => state.free_buildings += 1,
+            // Offers look at the rings completed
+            Reward::Unlock(_) => {}
+            Reward::Modifier(modifier) => state.modifiers.push(*modifier),
+        }
+    }
+}
+
+/// Rings completed in `state`, counting rubble as built on.
+pub fn completed_rings(state: &State) -> u32 {
+    let built = |coordinates| {
+        state.spiral.contains_key(&coordinates) || state.rubble.contains(&coordinates)
+    };
+    let mut rings = state.rings;
+    loop {
+        let n = rings as i32 + 1;
+        if !(-n..=n).all(|x| (-n..=n).all(|y| built((x, y)))) {
+            return rings;
+        }
+        rings += 1;
+    }
+}
+
+/// What completing `ring` gives.
+pub fn rewards(ring: u32) -> Vec<Reward> {
+    match ring {
+        1 => vec![Reward::Unlock(Building::Market), Reward::FreeBuilding],
+        2 => vec![Reward::Modifier(Modifier::Granaries), Reward::FreeBuilding],
+        3 => vec![Reward::Modifier(Modifier::Harvest), Reward::FreeBuilding],
+        _ => vec![Reward::FreeBuilding],
+    }
+}
+
+/// Whether `building` can be offered once `rings` rings are complete.
+pub fn is_unlocked(building: Building, rings: u32) -> bool {
+    (rings + 1..=SPECIAL_RINGS).all(|ring| !rewards(ring).contains(&Reward::Unlock(building)))
+}
+
+#[test]
+fn test_milestones() {
+    assert!(!is_unlocked(Building::Market, 0));
+    assert!(is_unlocked(Building::Market, 1));
+    assert!(is_unlocked(Building::Farm, 0));
+
+    let mut state = State::initialize();
+    for reward in rewards(2) {
+        reward.apply(&mut state);
+    }
+    assert_eq!(state.free_buildings, 1);
+    assert_eq!(state.modifiers, vec![Modifier::Granaries]);
+
+    // Eight more cells close the first ring at (1, 1)
+    let mut state = State::initialize();
+    state.owned_resources.wood = 20;
+    for _ in 0..8 {
+        state = state.turn(Building::House);
+    }
+    assert_eq!(state.current_position, (1, 1));
+    assert_eq!(state.rings, 1);
+    assert_eq!(state.free_buildings, 1);
+    let wood = state.owned_resources.wood;
+    state = state.turn(Building::House);
+    assert_eq!(state.free_buildings, 0);
+    assert_eq!(state.owned_resources.wood, wood);
+}
@@ -0,0 +1,171 @@
+//! Perks drafted before the first turn. The one picked changes the rules
+//! for the whole run: what buildings cost, what houses hold, how many
+//! buildings are offered or how neighbours affect production.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::buildings::Building;
+use crate::resources::Resources;
+use crate::states::State;
+
+/// Perks proposed in the draft.
+pub const DRAFT_SIZE: usize = 3;
+/// Wood given by the head start perk.
+const HEAD_START_WOOD: u32 = 3;
+/// Buildings offered each turn, without and with the wide offers perk.
+const OFFER_SIZE: usize = 2;
+const WIDE_OFFER_SIZE: usize = 3;
+/// Most buildings an offer can show, whatever the perks.
+pub const MAX_OFFER_SIZE: usize = WIDE_OFFER_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Perk {
+    /// Houses get one more room per level of each workshop next to them.
+    WorkshopHouses,
+    /// The run starts with some wood.
+    HeadStart,
+    /// Each offer shows one more building.
+    WideOffers,
+    /// Forests and quarries next to each other produce as much as alone.
+    Harmony,
+    /// Warehouses and markets cost one rock less.
+    Masonry,
+}
+
+impl Perk {
+    pub const ALL: [Perk; 5] = [
+        Perk::WorkshopHouses,
+        Perk::HeadStart,
+        Perk::WideOffers,
+        Perk::Harmony,
+        Perk::Masonry,
+    ];
+
+    /// Perks proposed before the first turn of a game with this seed. The
+    /// draft has its own generator so that it does not change the offers.
+    pub fn draft(seed: u64) -> Vec<Self> {
+        let mut rng = StdRng::seed_from_u64(seed ^ 0x5045_524B);
+        Self::ALL
+            .choose_multiple(&mut rng, DRAFT_SIZE)
+            .copied()
+            .collect()
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|p| p == self).unwrap()
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Perk::WorkshopHouses => {
+                String::from("🪚 Workshops also boost houses: +1 room 👥 per workshop level")
+            }
+            Perk::HeadStart => format!("🪵 Start with {HEAD_START_WOOD} wood"),
+            Perk::WideOffers => format!("🃏 Offers show {WIDE_OFFER_SIZE} buildings"),
+            Perk::Harmony => String::from("🤝 Forests and quarries no longer slow each other down"),
+            Perk::Masonry => String::from("🧱 Warehouses and markets cost 1 rock 🪨 less"),
+        }
+    }
+
+    /// What the perk does once, when it is picked.
+    pub fn apply(&self, state: &mut State) {
+        if *self == Perk::HeadStart {
+            state.owned_resources.wood += HEAD_START_WOOD;
+        }
+    }
+
+    fn adjust_cost(&self, building: Building, cost: Vec<Resources>) -> Vec<Resources> {
+        match (*self, building) {
+            (Perk::Masonry, Building::Warehouse | Building::Market) => cost
+                .into_iter()
+                .filter_map(|resource| match resource {
+                    Resources::Rock(n) if n <= 1 => None,
+                    Resources::Rock(n) => Some(Resources::Rock(n - 1)),
+                    other => Some(other),
+                })
+                .collect(),
+            _ => cost,
+        }
+    }
+
+    fn housing_bonus(&self, state: &State, coordinates: (i32, i32)) -> u32 {
+        if *self != Perk::WorkshopHouses || state.spiral.get(&coordinates) != Some(&Building::House)
+        {
+            return 0;
+        }
+        let (x, y) = coordinates;
+        [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .iter()
+            .filter(|&neighbour| {
+                state.spiral.get(neighbour) == Some(&Building::Workshop)
+                    && !state.inactive.contains(neighbour)
+            })
+            .map(|&neighbour| state.level(neighbour))
+            .sum()
+    }
+}
+
+/// Cost of `building` once every perk of the run has been applied.
+pub fn cost(state: &State, building: Building) -> Vec<Resources> {
+    state.perks.iter().fold(building.cost(), |cost, perk| {
+        perk.adjust_cost(building, cost)
+    })
+}
+
+/// Room for people in the building at `coordinates`, with the perks.
+pub fn housing(state: &State, coordinates: (i32, i32)) -> u32 {
+    let Some(building) = state.spiral.get(&coordinates) else {
+        return 0;
+    };
+    let bonus: u32 = state
+        .perks
+        .iter()
+        .map(|perk| perk.housing_bonus(state, coordinates))
+        .sum();
+    building.housing(state.level(coordinates)) + bonus
+}
+
+/// Buildings in each offer.
+pub fn offer_size(state: &State) -> usize {
+    if state.perks.contains(&Perk::WideOffers) {
+        WIDE_OFFER_SIZE
+    } else {
+        OFFER_SIZE
+    }
+}
+
+#[test]
+fn test_perks() {
+    let draft = Perk::draft(42);
+    assert_eq!(draft.len(), DRAFT_SIZE);
+    assert_eq!(draft, Perk::draft(42));
+    assert!(
+        draft
+            .iter()
+            .enumerate()
+            .all(|(i, perk)| !draft[i + 1..].contains(perk))
+    );
+
+    let mut state = State::initialize();
+    state.spiral.insert((1, 0), Building::Workshop);
+    state.levels.insert((1, 0), 2);
+    assert_eq!(housing(&state, (0, 0)), 2);
+    assert_eq!(
+        cost(&state, Building::Warehouse),
+        vec![Resources::Wood(2), Resources::Rock(1)]
+    );
+    assert_eq!(offer_size(&state), OFFER_SIZE);
+
+    state.perks = vec![Perk::WorkshopHouses, Perk::Masonry, Perk::WideOffers];
+    assert_eq!(housing(&state, (0, 0)), 4);
+    assert_eq!(housing(&state, (1, 0)), 0);
+    assert_eq!(cost(&state, Building::Warehouse), vec![Resources::Wood(2)]);
+    assert_eq!(
+        cost(&state, Building::Market),
+        vec![Resources::Wood(2), Resources::Rock(1)]
+    );
+    assert_eq!(offer_size(&state), WIDE_OFFER_SIZE);
+}
@@ -0,0 +1,188 @@
+//! The player's profile, kept in a local file between runs. Finished runs
+//! earn stars, which buy permanent unlocks applied at the start of every
+//! new run.
+
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::buildings::Building;
+use crate::states::State;
+
+/// Where the terminal game keeps the profile unless told otherwise.
+pub const DEFAULT_PROFILE_PATH: &str = "spiral_city_profile.json";
+/// Times the reroll unlock can be bought.
+const MAX_REROLLS: u32 = 3;
+/// Goods added to the starting stock by the matching unlocks.
+const STARTING_BONUS: u32 = 5;
+
+/// Permanent bonuses bought with stars.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Unlock {
+    /// Runs start with more wood.
+    StartingWood,
+    /// Runs start with more food.
+    StartingFood,
+    /// One more reroll of the offer per run.
+    Reroll,
+    /// This kind of building is in the offers from the first turn.
+    Building(Building),
+}
+
+impl Unlock {
+    pub const ALL: [Unlock; 4] = [
+        Unlock::StartingWood,
+        Unlock::StartingFood,
+        Unlock::Reroll,
+        Unlock::Building(Building::Market),
+    ];
+
+    /// Price in stars.
+    pub fn cost(&self) -> u32 {
+        match *self {
+            Unlock::StartingWood | Unlock::StartingFood => 20,
+            Unlock::Reroll => 30,
+            Unlock::Building(_) => 50,
+        }
+    }
+
+    /// Times it can be bought.
+    pub fn limit(&self) -> u32 {
+        match *self {
+            Unlock::Reroll => MAX_REROLLS,
+            _ => 1,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Unlock::StartingWood => format!("🪵 Start with {STARTING_BONUS} more wood"),
+            Unlock::StartingFood => format!("🍞 Start with {STARTING_BONUS} more food"),
+            Unlock::Reroll => String::from("🎲 One more reroll of the offer per run"),
+            Unlock::Building(building) => {
+                format!(
+                    "🔓 {} offered from the start",
+                    building.building_to_string()
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Stars earned and not spent yet.
+    pub stars: u32,
+    pub runs: u32,
+    pub best_turn: u32,
+    /// Unlocks bought, once per purchase.
+    pub unlocks: Vec<Unlock>,
+}
+
+impl Profile {
+    /// Read the profile at `path`, or start a new one if there is none.
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|error| error.to_string()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(path, text).map_err(|error| error.to_string())
+    }
+
+    /// Times `unlock` was bought.
+    pub fn owned(&self, unlock: Unlock) -> u32 {
+        self.unlocks
+            .iter()
+            .filter(|&&owned| owned == unlock)
+            .count() as u32
+    }
+
+    pub fn buy(&mut self, unlock: Unlock) -> Result<(), String> {
+        if self.owned(unlock) >= unlock.limit() {
+            return Err(String::from("this unlock cannot be bought again"));
+        }
+        if self.stars < unlock.cost() {
+            return Err(format!("this unlock costs {} ⭐", unlock.cost()));
+        }
+        self.stars -= unlock.cost();
+        self.unlocks.push(unlock);
+        Ok(())
+    }
+
+    /// Stars earned by a finished run: one per turn, ten per completed ring
+    /// and twenty per wonder standing.
+    pub fn score(state: &State) -> u32 {
+        state.turn + 10 * state.rings + 20 * state.wonders.len() as u32
+    }
+
+    /// Count a finished run and return the stars it earned.
+    pub fn record(&mut self, state: &State) -> u32 {
+        let earned = Self::score(state);
+        self.stars += earned;
+        self.runs += 1;
+        self.best_turn = self.best_turn.max(state.turn);
+        earned
+    }
+
+    /// Give a new run the bonuses bought so far.
+    pub fn apply(&self, state: &mut State) {
+        for unlock in &self.unlocks {
+            match *unlock {
+                Unlock::StartingWood => state.owned_resources.wood += STARTING_BONUS,
+                Unlock::StartingFood => state.owned_resources.food += STARTING_BONUS,
+                Unlock::Reroll => state.rerolls += 1,
+                Unlock::Building(building) => state.unlocked.push(building),
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        println!("\nProfile");
+        println!("-------");
+        println!(
+            "⭐ {} star(s) | {} run(s) | best run : {} turn(s)",
+            self.stars, self.runs, self.best_turn
+        );
+        println!("Unlocks:");
+        for (i, unlock) in Unlock::ALL.iter().enumerate() {
+            println!(
+                "[{}] {} — {} ⭐ ({}/{} owned)",
+                i + 1,
+                unlock.describe(),
+                unlock.cost(),
+                self.owned(*unlock),
+                unlock.limit()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_profile() {
+    let mut profile = Profile::default();
+    assert!(profile.buy(Unlock::Reroll).is_err());
+
+    let mut state = State::initialize();
+    state.turn = 40;
+    state.rings = 1;
+    assert_eq!(profile.record(&state), 50);
+    assert_eq!((profile.runs, profile.best_turn), (1, 40));
+
+    profile.buy(Unlock::Building(Building::Market)).unwrap();
+    assert_eq!(profile.stars, 0);
+    profile.stars = 100;
+    assert!(profile.buy(Unlock::Building(Building::Market)).is_err());
+
+    let text = serde_json::to_string(&profile).unwrap();
+    assert_eq!(serde_json::from_str::<Profile>(&text).unwrap(), profile);
+
+    let mut state = State::initialize();
+    profile.apply(&mut state);
+    assert_eq!(state.unlocked, vec![Building::Market]);
+}
@@ -0,0 +1,525 @@
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::buildings::Building;
+use crate::contracts::Contract;
+use crate::districts::{self, District};
+use crate::events::Event;
+use crate::fields;
+use crate::game::{Action, Game};
+use crate::milestones::{Modifier, Reward};
+use crate::perks::Perk;
+use crate::resources::{GlobalResources, Good, Resources};
+use crate::states::{State, Winding};
+use crate::terrain::Terrain;
+use crate::tributes::Tribute;
+
+/// Version of the JSON-lines protocol, sent with every message.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CellView {
+    pub x: i32,
+    pub y: i32,
+    pub building: Building,
+    pub level: u32,
+    pub workers: u32,
+    pub pollution: i32,
+    pub happiness: i32,
+    pub terrain: Terrain,
+    /// District the building belongs to, if its cluster is large enough.
+    pub district: Option<District>,
+}
+
+/// Serialisable snapshot of a `State`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StateView {
+    pub turn: u32,
+    pub resources: GlobalResources,
+    pub spiral: Vec<CellView>,
+    pub next_position: (i32, i32),
+    pub next_terrain: Terrain,
+    pub last_turn: Vec<Resources>,
+    pub inactive: Vec<(i32, i32)>,
+    pub last_turn_lost: Vec<Resources>,
+    pub rubble: Vec<(i32, i32)>,
+    pub priorities: Vec<Building>,
+    pub growth_rate: u32,
+    pub growth_progress: u32,
+    pub happiness: i32,
+    /// Price of one unit of each good, empty without a market.
+    pub trade_prices: Vec<(Good, u32)>,
+    pub storm_turns: u32,
+    pub boom_turns: u32,
+    pub events: Vec<(u32, Event)>,
+    pub rings: u32,
+    pub free_buildings: u32,
+    pub modifiers: Vec<Modifier>,
+    pub winding: Winding,
+    pub steering: bool,
+    pub skip_tokens: u32,
+    pub skipped: Vec<(i32, i32)>,
+    pub frontier: bool,
+    /// Empty cells next to the city, where buildings go in frontier mode.
+    pub frontier_cells: Vec<(i32, i32)>,
+    /// Wonders standing, with the cells they span.
+    pub wonders: Vec<(Building, Vec<(i32, i32)>)>,
+    /// Contracts accepted and not settled yet.
+    pub contracts: Vec<Contract>,
+    pub contract_offer: Option<Contract>,
+    pub tribute: Tribute,
+    pub tribute_failed: bool,
+    pub rerolls: u32,
+    /// Buildings offered from the start thanks to the player's profile.
+    pub unlocked: Vec<Building>,
+    pub perks: Vec<Perk>,
+    /// Perks one of which can be picked before the first turn.
+    pub perk_draft: Vec<Perk>,
+}
+
+impl StateView {
+    pub fn from_state(state: &State) -> Self {
+        let mut spiral: Vec<CellView> = state
+            .spiral
+            .iter()
+            .map(|(&(x, y), &building)| CellView {
+                x,
+                y,
+                building,
+                level: state.level((x, y)),
+                workers: state.workers_on((x, y)),
+                pollution: fields::pollution_at(state, (x, y)),
+                happiness: fields::happiness_at(state, (x, y)),
+                terrain: state.terrain((x, y)),
+                district: districts::district_at(state, (x, y)),
+            })
+            .collect();
+        spiral.sort_by_key(|cell| (cell.x, cell.y));
+        let mut inactive: Vec<(i32, i32)> = state.inactive.iter().copied().collect();
+        inactive.sort();
+        let mut rubble: Vec<(i32, i32)> = state.rubble.iter().copied().collect();
+        rubble.sort();
+        let mut skipped: Vec<(i32, i32)> = state.skipped.iter().copied().collect();
+        skipped.sort();
+        Self {
+            turn: state.turn,
+            resources: state.owned_resources.clone(),
+            spiral,
+            next_position: state.get_next_position(),
+            next_terrain: state.terrain(state.get_next_position()),
+            last_turn: state.delta_production.clone(),
+            inactive,
+            last_turn_lost: state.lost_production.clone(),
+            rubble,
+            priorities: state.priorities.clone(),
+            growth_rate: state.growth_rate(),
+            growth_progress: state.growth_progress,
+            happiness: state.happiness,
+            trade_prices: Good::ALL
+                .iter()
+                .filter_map(|&good| Some((good, state.trade_price(good)?)))
+                .collect(),
+            storm_turns: state.storm_turns,
+            boom_turns: state.boom_turns,
+            events: state.events.clone(),
+            rings: state.rings,
+            free_buildings: state.free_buildings,
+            modifiers: state.modifiers.clone(),
+            winding: state.winding,
+            steering: state.steering,
+            skip_tokens: state.skip_tokens,
+            skipped,
+            frontier: state.frontier,
+            frontier_cells: if state.frontier {
+                state.frontier_cells()
+            } else {
+                vec![]
+            },
+            wonders: state.wonders.clone(),
+            contracts: state.contracts.clone(),
+            contract_offer: state.contract_offer.clone(),
+            tribute: state.tribute.clone(),
+            tribute_failed: state.tribute_failed,
+            rerolls: state.rerolls,
+            unlocked: state.unlocked.clone(),
+            perks: state.perks.clone(),
+            perk_draft: state.perk_draft.clone(),
+        }
+    }
+}
+
+/// Messages sent by the game.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    State {
+        state: Box<StateView>,
+    },
+    Offer {
+        buildings: Vec<Building>,
+    },
+    LegalMoves {
+        moves: Vec<usize>,
+    },
+    TurnResult {
+        built: Building,
+        level: u32,
+        position: (i32, i32),
+        delta_production: Vec<Resources>,
+        lost_production: Vec<Resources>,
+        event: Option<Event>,
+        /// Ring of the spiral completed by this turn, and what it gave.
+        ring: Option<u32>,
+        rewards: Vec<Reward>,
+        /// Contracts settled by this turn, with whether they were met.
+        contracts: Vec<(Contract, bool)>,
+    },
+    Demolished {
+        position: (i32, i32),
+    },
+    /// Workers have been reassigned following the new staffing order.
+    Prioritised {
+        priorities: Vec<Building>,
+    },
+    Traded {
+        paid: Resources,
+        received: Resources,
+    },
+    /// The spiral now turns this way at its corners.
+    Steered {
+        winding: Winding,
+    },
+    Skipped {
+        position: (i32, i32),
+    },
+    /// The contract offered is now running.
+    Accepted {
+        contract: Contract,
+    },
+    /// The offer was drawn again.
+    Rerolled {
+        buildings: Vec<Building>,
+    },
+    /// This perk now applies to the whole run.
+    Picked {
+        perk: Perk,
+    },
+    GameOver {
+        turn: u32,
+        reason: String,
+    },
+    Error {
+        message: String,
+    },
+    /// Server mode: the connection is now attached to session `id`.
+    Session {
+        id: u32,
+    },
+    /// Server mode: session `id` has been closed.
+    Closed {
+        id: u32,
+    },
+}
+
+/// Messages read from the player, one per line.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Choose {
+        index: usize,
+    },
+    /// Build the offered building at `index` on the rubble at (x, y).
+    Rebuild {
+        index: usize,
+        x: i32,
+        y: i32,
+    },
+    Demolish {
+        x: i32,
+        y: i32,
+    },
+    /// Upgrade the building at (x, y) to its next tier, this takes the turn.
+    Upgrade {
+        x: i32,
+        y: i32,
+    },
+    /// Staff this kind of building first, this does not take the turn.
+    Prioritise {
+        building: Building,
+    },
+    /// Buy `amount` units of `get` at the market with `give`, this does not
+    /// take the turn.
+    Trade {
+        give: Good,
+        get: Good,
+        amount: u32,
+    },
+    /// Build the offered building at `index` on the frontier cell (x, y), in
+    /// frontier mode.
+    BuildAt {
+        index: usize,
+        x: i32,
+        y: i32,
+    },
+    /// Turn the other way at the coming corner, this does not take the turn.
+    Steer,
+    /// Leave the next cell empty for later, this does not take the turn.
+    Skip,
+    /// Take on the contract offered, this does not take the turn.
+    Accept,
+    /// Draw a new offer, this does not take the turn.
+    Reroll,
+    /// Keep the drafted perk at `index` for the whole run, before the first
+    /// turn. This does not take the turn.
+    PickPerk {
+        index: usize,
+    },
+    Quit,
+    /// Server mode: start a new session, optionally seeded, steered or on
+    /// the frontier.
+    Create {
+        seed: Option<u64>,
+        #[serde(default)]
+        steering: bool,
+        #[serde(default)]
+        frontier: bool,
+    },
+    /// Server mode: attach to an existing session.
+    Resume {
+        id: u32,
+    },
+    /// Server mode: close the attached session.
+    Close,
+}
+
+#[derive(Serialize)]
+struct OutgoingEnvelope<'a> {
+    version: u32,
+    #[serde(flatten)]
+    message: &'a ServerMessage,
+}
+
+#[derive(Deserialize)]
+struct IncomingEnvelope {
+    version: Option<u32>,
+    #[serde(flatten)]
+    message: ClientMessage,
+}
+
+pub fn encode(message: &ServerMessage) -> String {
+    let envelope = OutgoingEnvelope {
+        version: PROTOCOL_VERSION,
+        message,
+    };
+    serde_json::to_string(&envelope).expect("server messages are always serialisable")
+}
+
+pub fn decode(line: &str) -> Result<ClientMessage, String> {
+    let envelope: IncomingEnvelope =
+        serde_json::from_str(line).map_err(|e| format!("invalid message: {e}"))?;
+    match envelope.version {
+        Some(version) if version != PROTOCOL_VERSION => Err(format!(
+            "unsupported protocol version {version}, expected {PROTOCOL_VERSION}"
+        )),
+        _ => Ok(envelope.message),
+    }
+}
+
+/// State, offer and legal moves describing the decision the player faces,
+/// or the end of the game if a tribute was not paid, or if none of the
+/// offered buildings can be built and nothing can be upgraded, traded or
+/// skipped.
+pub fn situation(game: &Game) -> Vec<ServerMessage> {
+    if game.state.tribute_failed {
+        return vec![ServerMessage::GameOver {
+            turn: game.state.turn,
+            reason: format!(
+                "the tribute due at turn {} could not be paid",
+                game.state.tribute.due
+            ),
+        }];
+    }
+    if game.is_over() {
+        return vec![ServerMessage::GameOver {
+            turn: game.state.turn,
+            reason: String::from(
+                "none of the offered buildings can be built, and nothing can be upgraded, traded or skipped",
+            ),
+        }];
+    }
+    let moves = game
+        .legal_moves()
+        .iter()
+        .enumerate()
+        .filter(|(_, legal)| **legal)
+        .map(|(index, _)| index)
+        .collect();
+    vec![
+        ServerMessage::State {
+            state: Box::new(StateView::from_state(&game.state)),
+        },
+        ServerMessage::Offer {
+            buildings: game.offer.clone(),
+        },
+        ServerMessage::LegalMoves { moves },
+    ]
+}
+
+/// Apply a player message to the game and return the answer to send back.
+pub fn respond(game: &mut Game, message: ClientMessage) -> ServerMessage {
+    match message {
+        ClientMessage::Choose { index } => play(game, Action::Build { index }),
+        ClientMessage::Rebuild { index, x, y } => play(game, Action::Rebuild { index, x, y }),
+        ClientMessage::Demolish { x, y } => play(game, Action::Demolish { x, y }),
+        ClientMessage::Upgrade { x, y } => play(game, Action::Upgrade { x, y }),
+        ClientMessage::Prioritise { building } => play(game, Action::Prioritise { building }),
+        ClientMessage::Trade { give, get, amount } => {
+            play(game, Action::Trade { give, get, amount })
+        }
+        ClientMessage::BuildAt { index, x, y } => play(game, Action::BuildAt { index, x, y }),
+        ClientMessage::Steer => play(game, Action::Steer),
+        ClientMessage::Skip => play(game, Action::Skip),
+        ClientMessage::Accept => play(game, Action::Accept),
+        ClientMessage::Reroll => play(game, Action::Reroll),
+        ClientMessage::PickPerk { index } => play(game, Action::PickPerk { index }),
+        ClientMessage::Quit => ServerMessage::GameOver {
+            turn: game.state.turn,
+            reason: String::from("quit"),
+        },
+        ClientMessage::Create { .. } | ClientMessage::Resume { .. } | ClientMessage::Close => {
+            ServerMessage::Error {
+                message: String::from("sessions are only available in server mode"),
+            }
+        }
+    }
+}
+
+fn play(game: &mut Game, action: Action) -> ServerMessage {
+    let before = game.state.owned_resources.clone();
+    let next_position = game.state.get_next_position();
+    if let Err(message) = game.play(action) {
+        return ServerMessage::Error { message };
+    }
+    match action {
+        Action::Demolish { x, y } => ServerMessage::Demolished { position: (x, y) },
+        Action::Prioritise { .. } => ServerMessage::Prioritised {
+            priorities: game.state.priorities.clone(),
+        },
+        Action::Trade { give, get, .. } => {
+            let after = &game.state.owned_resources;
+            ServerMessage::Traded {
+                paid: give.resources((before.amount(give) - after.amount(give)) as i32),
+                received: get.resources((after.amount(get) - before.amount(get)) as i32),
+            }
+        }
+        Action::Steer => ServerMessage::Steered {
+            winding: game.state.winding,
+        },
+        Action::Skip => ServerMessage::Skipped {
+            position: next_position,
+        },
+        Action::Accept => ServerMessage::Accepted {
+            contract: game.state.contracts.last().unwrap().clone(),
+        },
+        Action::Reroll => ServerMessage::Rerolled {
+            buildings: game.offer.clone(),
+        },
+        Action::PickPerk { .. } => ServerMessage::Picked {
+            perk: *game.state.perks.last().unwrap(),
+        },
+        Action::Build { .. }
+        | Action::Rebuild { .. }
+        | Action::BuildAt { .. }
+        | Action::Upgrade { .. } => {
+            let record = game.history.last().unwrap();
+            ServerMessage::TurnResult {
+                built: record.built,
+                level: record.level,
+                position: record.position,
+                delta_production: record.delta_production.clone(),
+                lost_production: record.lost_production.clone(),
+                event: record.event.clone(),
+                ring: record.ring,
+                rewards: record.rewards.clone(),
+                contracts: record.contracts.clone(),
+            }
+        }
+    }
+}
+
+/// Play a whole game over JSON lines: the situation is written before each
+/// choice, then one `ClientMessage` is read per line until the game ends, the
+/// player quits or the input is closed.
+pub fn play_json<R: BufRead, W: Write>(game: &mut Game, input: R, mut output: W) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        let messages = situation(game);
+        for message in &messages {
+            writeln!(output, "{}", encode(message))?;
+        }
+        output.flush()?;
+        if game.is_over() {
+            return Ok(());
+        }
+
+        loop {
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let answer = match decode(&line) {
+                Ok(message) => respond(game, message),
+                Err(message) => ServerMessage::Error { message },
+            };
+            writeln!(output, "{}", encode(&answer))?;
+            output.flush()?;
+            match answer {
+                ServerMessage::Error { .. } => continue,
+                ServerMessage::GameOver { .. } => return Ok(()),
+                _ => break,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_play_json() {
+    let mut game = Game::new(7);
+    let input = "{\"version\": 1, \"type\": \"choose\", \"index\": 5}\n\
+                 {\"version\": 2, \"type\": \"quit\"}\n\
+                 {\"type\": \"quit\"}\n";
+    let mut output = Vec::new();
+    play_json(&mut game, input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let types: Vec<String> = output
+        .lines()
+        .map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["version"], PROTOCOL_VERSION);
+            value["type"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(
+        types,
+        [
+            "state",
+            "offer",
+            "legal_moves",
+            "error",
+            "error",
+            "game_over"
+        ]
+    );
+
+    let index = game.legal_moves().iter().position(|&legal| legal).unwrap();
+    let answer = respond(
+        &mut game,
+        decode(&format!("{{\"type\":\"choose\",\"index\":{index}}}")).unwrap(),
+    );
+    assert!(matches!(answer, ServerMessage::TurnResult { .. }));
+    assert_eq!(game.state.turn, 1);
+}
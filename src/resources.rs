@@ -1,9 +1,27 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+/// Storage available for each resource before any warehouse is built.
+pub const BASE_STORAGE: u32 = 20;
+
+/// Default market rates, see `TradeRates`.
+pub const DEFAULT_TRADE_RATES: TradeRates = TradeRates {
+    base: 3,
+    increase: 1,
+    recovery: 1,
+};
+
+#[derive(Debug, Clone, Serialize)]
 pub struct GlobalResources {
     pub total_people: u32,
     pub occupied_people: u32,
+    pub housing: u32,
     pub wood: u32,
     pub rock: u32,
+    pub food: u32,
+    pub hungry_people: u32,
+    pub wood_cap: u32,
+    pub rock_cap: u32,
+    pub food_cap: u32,
 }
 
 impl GlobalResources {
@@ -11,17 +29,228 @@ impl GlobalResources {
         Self {
             total_people: 1,
             occupied_people: 0,
+            housing: 2,
             wood: 0,
             rock: 0,
+            food: 5,
+            hungry_people: 0,
+            wood_cap: BASE_STORAGE,
+            rock_cap: BASE_STORAGE,
+            food_cap: BASE_STORAGE,
+        }
+    }
+
+    /// Throw away what does not fit in storage, and return what was lost.
+    pub fn apply_storage_caps(&mut self) -> Vec<Resources> {
+        let mut lost = vec![];
+        if self.wood > self.wood_cap {
+            lost.push(Resources::Wood((self.wood - self.wood_cap) as i32));
+            self.wood = self.wood_cap;
+        }
+        if self.rock > self.rock_cap {
+            lost.push(Resources::Rock((self.rock - self.rock_cap) as i32));
+            self.rock = self.rock_cap;
+        }
+        if self.food > self.food_cap {
+            lost.push(Resources::Food((self.food - self.food_cap) as i32));
+            self.food = self.food_cap;
+        }
+        lost
+    }
+
+    /// People that are neither working nor too hungry to work.
+    pub fn available_people(&self) -> u32 {
+        self.total_people
+            .saturating_sub(self.occupied_people)
+            .saturating_sub(self.hungry_people)
+    }
+
+    /// Whether a one-off cost (building, upgrade) can be paid right now.
+    pub fn can_afford(&self, cost: &[Resources]) -> bool {
+        cost.iter().all(|resource| match *resource {
+            Resources::WorkingPeople(_) => true,
+            Resources::Wood(n) => self.wood >= n as u32,
+            Resources::Rock(n) => self.rock >= n as u32,
+            Resources::Food(n) => self.food >= n as u32,
+        })
+    }
+
+    /// Pay a one-off cost, check `can_afford` first. People are not spent,
+    /// they are assigned to buildings every turn.
+    pub fn pay(&mut self, cost: &[Resources]) {
+        for resource in cost {
+            match *resource {
+                Resources::WorkingPeople(_) => {}
+                Resources::Wood(n) => self.wood -= n as u32,
+                Resources::Rock(n) => self.rock -= n as u32,
+                Resources::Food(n) => self.food -= n as u32,
+            }
+        }
+    }
+
+    /// Add a one-off income, `apply_storage_caps` throws away what does
+    /// not fit.
+    pub fn receive(&mut self, income: &[Resources]) {
+        for resource in income {
+            match *resource {
+                Resources::WorkingPeople(_) => {}
+                Resources::Wood(n) => self.wood += n as u32,
+                Resources::Rock(n) => self.rock += n as u32,
+                Resources::Food(n) => self.food += n as u32,
+            }
+        }
+    }
+
+    /// Take away a one-off loss, down to nothing if there is not enough.
+    pub fn lose(&mut self, loss: &[Resources]) {
+        for resource in loss {
+            match *resource {
+                Resources::WorkingPeople(_) => {}
+                Resources::Wood(n) => self.wood = self.wood.saturating_sub(n as u32),
+                Resources::Rock(n) => self.rock = self.rock.saturating_sub(n as u32),
+                Resources::Food(n) => self.food = self.food.saturating_sub(n as u32),
+            }
+        }
+    }
+
+    pub fn amount(&self, good: Good) -> u32 {
+        match good {
+            Good::Wood => self.wood,
+            Good::Rock => self.rock,
+            Good::Food => self.food,
+        }
+    }
+
+    pub fn cap(&self, good: Good) -> u32 {
+        match good {
+            Good::Wood => self.wood_cap,
+            Good::Rock => self.rock_cap,
+            Good::Food => self.food_cap,
+        }
+    }
+
+    fn amount_mut(&mut self, good: Good) -> &mut u32 {
+        match good {
+            Good::Wood => &mut self.wood,
+            Good::Rock => &mut self.rock,
+            Good::Food => &mut self.food,
+        }
+    }
+
+    /// Exchange `paid` units of `give` for `received` units of `get`, or do
+    /// nothing and explain why it is not possible.
+    pub fn exchange(
+        &mut self,
+        give: Good,
+        paid: u32,
+        get: Good,
+        received: u32,
+    ) -> Result<(), String> {
+        if self.amount(give) < paid {
+            return Err(format!("this trade costs {paid} {}", give.symbol()));
+        }
+        if self.amount(get) + received > self.cap(get) {
+            return Err(format!(
+                "not enough storage for {received} {}",
+                get.symbol()
+            ));
+        }
+        *self.amount_mut(give) -= paid;
+        *self.amount_mut(get) += received;
+        Ok(())
+    }
+
+    /// Pay a per-turn upkeep in full, or pay nothing and return false.
+    pub fn pay_upkeep(&mut self, upkeep: &[Resources]) -> bool {
+        let affordable = upkeep.iter().all(|resource| match *resource {
+            Resources::Wood(n) => self.wood >= n as u32,
+            Resources::Rock(n) => self.rock >= n as u32,
+            Resources::Food(n) => self.food >= n as u32,
+            Resources::WorkingPeople(_) => true,
+        });
+        if !affordable {
+            return false;
+        }
+        for resource in upkeep {
+            match *resource {
+                Resources::Wood(n) => self.wood -= n as u32,
+                Resources::Rock(n) => self.rock -= n as u32,
+                Resources::Food(n) => self.food -= n as u32,
+                Resources::WorkingPeople(_) => {}
+            }
+        }
+        true
+    }
+
+    /// Each person eats one food per turn. People who cannot be fed go
+    /// hungry and cannot be put to work until there is food again.
+    pub fn feed_people(&mut self) {
+        if self.food >= self.total_people {
+            self.food -= self.total_people;
+            self.hungry_people = 0;
+        } else {
+            self.hungry_people = self.total_people - self.food;
+            self.food = 0;
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Resources that can be traded at a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Good {
+    Wood,
+    Rock,
+    Food,
+}
+
+impl Good {
+    pub const ALL: [Good; 3] = [Good::Wood, Good::Rock, Good::Food];
+
+    /// Good whose name (e.g. "wood") is given, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|good| format!("{good:?}").eq_ignore_ascii_case(name))
+    }
+
+    pub fn symbol(&self) -> char {
+        self.resources(0).resource_to_symbol()
+    }
+
+    pub fn resources(&self, n: i32) -> Resources {
+        match *self {
+            Good::Wood => Resources::Wood(n),
+            Good::Rock => Resources::Rock(n),
+            Good::Food => Resources::Food(n),
+        }
+    }
+}
+
+/// How a market prices goods: buying one unit of a good costs `base` units
+/// of another, minus one per market tier above the first. Each unit bought
+/// raises the price of that good by `increase`, and prices come back down by
+/// `recovery` every turn.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TradeRates {
+    pub base: u32,
+    pub increase: u32,
+    pub recovery: u32,
+}
+
+impl TradeRates {
+    pub fn base_price(&self, market_level: u32) -> u32 {
+        self.base
+            .saturating_sub(market_level.saturating_sub(1))
+            .max(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum Resources {
     WorkingPeople(i32),
     Wood(i32),
     Rock(i32),
+    Food(i32),
 }
 
 impl Resources {
@@ -30,9 +259,42 @@ impl Resources {
             Resources::WorkingPeople(_) => '👥',
             Resources::Wood(_) => '🪵',
             Resources::Rock(_) => '🪨',
+            Resources::Food(_) => '🍞',
         }
     }
 
+    pub fn cost_to_string(cost: &[Self]) -> String {
+        let cost: Vec<String> = cost
+            .iter()
+            .map(|resource| {
+                let n = match *resource {
+                    Resources::WorkingPeople(n) => n,
+                    Resources::Wood(n) => n,
+                    Resources::Rock(n) => n,
+                    Resources::Food(n) => n,
+                };
+                format!("{n} {}", resource.resource_to_symbol())
+            })
+            .collect();
+        cost.join(" | ")
+    }
+
+    pub fn lost_to_string(lost_production: &[Self]) -> String {
+        let lost: Vec<String> = lost_production
+            .iter()
+            .map(|resource| {
+                let n = match *resource {
+                    Resources::WorkingPeople(n) => n,
+                    Resources::Wood(n) => n,
+                    Resources::Rock(n) => n,
+                    Resources::Food(n) => n,
+                };
+                format!("{} {n}", resource.resource_to_symbol())
+            })
+            .collect();
+        format!("{} lost, storage full 📦", lost.join(" | "))
+    }
+
     pub fn delta_to_string(delta_production: &[Self]) -> String {
         let mut res = String::new();
         if delta_production.is_empty() {
@@ -44,6 +306,7 @@ impl Resources {
                 Resources::WorkingPeople(n) => n,
                 Resources::Wood(n) => n,
                 Resources::Rock(n) => n,
+                Resources::Food(n) => n,
             };
             if diff == 0 {
                 unreachable!("diff of resource {resource:?} should not be 0!");
@@ -70,3 +333,31 @@ impl Resources {
         res
     }
 }
+
+#[test]
+fn test_feed_people() {
+    let mut resources = GlobalResources {
+        total_people: 3,
+        food: 5,
+        ..GlobalResources::initialize()
+    };
+    resources.feed_people();
+    assert_eq!((resources.food, resources.hungry_people), (2, 0));
+    resources.feed_people();
+    assert_eq!((resources.food, resources.hungry_people), (0, 1));
+    assert_eq!(resources.available_people(), 2);
+}
+
+#[test]
+fn test_apply_storage_caps() {
+    let mut resources = GlobalResources {
+        wood: 25,
+        rock: 20,
+        food: 3,
+        ..GlobalResources::initialize()
+    };
+    let lost = resources.apply_storage_caps();
+    assert_eq!(resources.wood, BASE_STORAGE);
+    assert_eq!(resources.rock, BASE_STORAGE);
+    assert!(matches!(lost[..], [Resources::Wood(5)]));
+}
@@ -0,0 +1,172 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use crate::protocol::{self, ClientMessage, ServerMessage};
+use crate::sessions::SharedSessions;
+
+/// Accept connections forever, one thread per connection. Each connection
+/// speaks the JSON-lines protocol and can create, resume and close sessions;
+/// sessions outlive the connection that created them.
+pub fn serve(listener: TcpListener, sessions: SharedSessions) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let sessions = sessions.clone();
+        thread::spawn(move || {
+            // A client hanging up mid-game is not an error for the server
+            let _ = handle_connection(stream, sessions);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, sessions: SharedSessions) -> io::Result<()> {
+    let mut output = stream.try_clone()?;
+    let mut attached: Option<u32> = None;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match protocol::decode(&line) {
+            Ok(message) => message,
+            Err(message) => {
+                send(&mut output, &[ServerMessage::Error { message }])?;
+                continue;
+            }
+        };
+
+        let mut sessions = sessions.lock().unwrap();
+        let answers = match (message, attached) {
+            (
+                ClientMessage::Create {
+                    seed,
+                    steering,
+                    frontier,
+                },
+                _,
+            ) => match sessions.create(seed.unwrap_or_else(rand::random), steering, frontier) {
+                Ok(id) => {
+                    attached = Some(id);
+                    let mut answers = vec![ServerMessage::Session { id }];
+                    answers.extend(protocol::situation(sessions.get(id).unwrap()));
+                    answers
+                }
+                Err(message) => vec![ServerMessage::Error { message }],
+            },
+            (ClientMessage::Resume { id }, _) => match sessions.get(id) {
+                Some(game) => {
+                    attached = Some(id);
+                    let mut answers = vec![ServerMessage::Session { id }];
+                    answers.extend(protocol::situation(game));
+                    answers
+                }
+                None => vec![ServerMessage::Error {
+                    message: format!("no session with id {id}"),
+                }],
+            },
+            (ClientMessage::Close, Some(id)) => {
+                sessions.close(id);
+                attached = None;
+                vec![ServerMessage::Closed { id }]
+            }
+            (ClientMessage::Quit, _) => return Ok(()),
+            (message, Some(id)) => match sessions.get_mut(id) {
+                Some(game) => {
+                    let answer = protocol::respond(game, message);
+                    let played = matches!(
+                        answer,
+                        ServerMessage::TurnResult { .. }
+                            | ServerMessage::Demolished { .. }
+                            | ServerMessage::Prioritised { .. }
+                            | ServerMessage::Traded { .. }
+                            | ServerMessage::Steered { .. }
+                            | ServerMessage::Skipped { .. }
+                            | ServerMessage::Accepted { .. }
+                            | ServerMessage::Rerolled { .. }
+                            | ServerMessage::Picked { .. }
+                    );
+                    let mut answers = vec![answer];
+                    if played {
+                        answers.extend(protocol::situation(game));
+                    }
+                    answers
+                }
+                None => {
+                    attached = None;
+                    vec![ServerMessage::Error {
+                        message: format!("session {id} has been closed"),
+                    }]
+                }
+            },
+            (_, None) => vec![ServerMessage::Error {
+                message: String::from("no session attached, send 'create' or 'resume' first"),
+            }],
+        };
+        drop(sessions);
+        send(&mut output, &answers)?;
+    }
+    Ok(())
+}
+
+fn send(output: &mut TcpStream, messages: &[ServerMessage]) -> io::Result<()> {
+    for message in messages {
+        writeln!(output, "{}", protocol::encode(message))?;
+    }
+    output.flush()
+}
+
+#[test]
+fn test_server_sessions() {
+    use crate::sessions::Sessions;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let sessions = Sessions::shared();
+    let server_sessions = sessions.clone();
+    thread::spawn(move || serve(listener, server_sessions));
+
+    fn exchange(stream: &mut TcpStream, line: &str, nb_answers: usize) -> Vec<serde_json::Value> {
+        writeln!(stream, "{line}").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        (0..nb_answers)
+            .map(|_| {
+                let mut answer = String::new();
+                reader.read_line(&mut answer).unwrap();
+                serde_json::from_str(&answer).unwrap()
+            })
+            .collect()
+    }
+
+    let mut first = TcpStream::connect(address).unwrap();
+    let answers = exchange(&mut first, r#"{"type": "create", "seed": 2}"#, 4);
+    assert_eq!(answers[0]["type"], "session");
+    let id = answers[0]["id"].as_u64().unwrap();
+    assert_eq!(answers[1]["type"], "state");
+    writeln!(first, r#"{{"type": "quit"}}"#).unwrap();
+
+    let mut second = TcpStream::connect(address).unwrap();
+    let answers = exchange(
+        &mut second,
+        &format!(r#"{{"type": "resume", "id": {id}}}"#),
+        4,
+    );
+    assert_eq!(answers[0]["id"], id);
+    let answers = exchange(&mut second, r#"{"type": "close"}"#, 1);
+    assert_eq!(answers[0]["type"], "closed");
+    assert!(sessions.lock().unwrap().get(id as u32).is_none());
+
+    let answers = exchange(
+        &mut second,
+        r#"{"type": "create", "seed": 2, "frontier": true}"#,
+        4,
+    );
+    assert_eq!(answers[1]["state"]["frontier"], true);
+    let answers = exchange(
+        &mut second,
+        r#"{"type": "create", "steering": true, "frontier": true}"#,
+        1,
+    );
+    assert_eq!(answers[0]["type"], "error");
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::game::Game;
+
+pub type SharedSessions = Arc<Mutex<Sessions>>;
+
+/// Independent games hosted by a long-running process, indexed by id.
+#[derive(Debug, Default)]
+pub struct Sessions {
+    next_id: u32,
+    games: HashMap<u32, Game>,
+}
+
+impl Sessions {
+    pub fn shared() -> SharedSessions {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Start a game with the rules asked for, see `Game::with_rules`.
+    pub fn create(&mut self, seed: u64, steering: bool, frontier: bool) -> Result<u32, String> {
+        let game = Game::with_rules(seed, steering, frontier)?;
+        self.next_id += 1;
+        self.games.insert(self.next_id, game);
+        Ok(self.next_id)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Game> {
+        self.games.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Game> {
+        self.games.get_mut(&id)
+    }
+
+    pub fn close(&mut self, id: u32) -> bool {
+        self.games.remove(&id).is_some()
+    }
+}
@@ -1,11 +1,46 @@
-use std::{collections::HashMap, io};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
 
-use enum_derived::Rand;
+use rand::Rng;
+use serde::Serialize;
 
-use crate::buildings::Building;
-use crate::resources::{GlobalResources, Resources};
+use crate::buildings::{Building, MAX_LEVEL};
+use crate::contracts::{Contract, MAX_CONTRACTS};
+use crate::districts::{self, District};
+use crate::events::Event;
+use crate::fields::{self, Field};
+use crate::game::Action;
+use crate::milestones::{self, Modifier};
+use crate::perks::{self, Perk};
+use crate::resources::{
+    BASE_STORAGE, DEFAULT_TRADE_RATES, GlobalResources, Good, Resources, TradeRates,
+};
+use crate::terrain::Terrain;
+use crate::tributes::Tribute;
 
-#[derive(Debug, Clone, Copy)]
+/// Wood paid to demolish a building.
+pub const DEMOLITION_COST: u32 = 1;
+
+/// Order in which buildings are staffed until the player changes it.
+pub const DEFAULT_PRIORITIES: [Building; 5] = [
+    Building::Farm,
+    Building::Fishery,
+    Building::Forest,
+    Building::Quarry,
+    Building::Workshop,
+];
+
+/// Skip tokens given to a game where the player steers the spiral.
+pub const SKIP_TOKENS: u32 = 3;
+
+/// Rings to complete before wonders can be offered.
+const WONDER_RING: u32 = 2;
+/// Percent chance of a wonder in each offer once they can be offered.
+const WONDER_CHANCE: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum Direction {
     Right,
     Left,
@@ -13,6 +48,68 @@ pub enum Direction {
     Down,
 }
 
+impl Direction {
+    /// Direction taken after a corner of the spiral winding this way.
+    pub fn turned(self, winding: Winding) -> Self {
+        match (self, winding) {
+            (Direction::Right, Winding::Clockwise)
+            | (Direction::Left, Winding::CounterClockwise) => Direction::Down,
+            (Direction::Left, Winding::Clockwise)
+            | (Direction::Right, Winding::CounterClockwise) => Direction::Up,
+            (Direction::Down, Winding::Clockwise) | (Direction::Up, Winding::CounterClockwise) => {
+                Direction::Left
+            }
+            (Direction::Up, Winding::Clockwise) | (Direction::Down, Winding::CounterClockwise) => {
+                Direction::Right
+            }
+        }
+    }
+
+    pub fn step(self, coordinates: (i32, i32)) -> (i32, i32) {
+        let (x, y) = coordinates;
+        match self {
+            Direction::Right => (x + 1, y),
+            Direction::Left => (x - 1, y),
+            Direction::Up => (x, y + 1),
+            Direction::Down => (x, y - 1),
+        }
+    }
+
+    /// Arrow shown on the next cell, coming this way and leaving `next`.
+    fn arrow(self, next: Direction) -> char {
+        match (self, next) {
+            (Direction::Right, Direction::Down) => '⮧',
+            (Direction::Right, Direction::Up) => '⮥',
+            (Direction::Left, Direction::Up) => '⮤',
+            (Direction::Left, Direction::Down) => '⮦',
+            (Direction::Down, Direction::Left) => '⮠',
+            (Direction::Down, Direction::Right) => '⮡',
+            (Direction::Up, Direction::Right) => '⮣',
+            (Direction::Up, Direction::Left) => '⮢',
+            (Direction::Down, _) => '🡻',
+            (Direction::Up, _) => '🡹',
+            (Direction::Left, _) => '🡸',
+            (Direction::Right, _) => '🡺',
+        }
+    }
+}
+
+/// Which way the spiral turns at its corners.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Winding {
+    pub fn reversed(self) -> Self {
+        match self {
+            Winding::Clockwise => Winding::CounterClockwise,
+            Winding::CounterClockwise => Winding::Clockwise,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct State {
     pub turn: u32,
@@ -22,7 +119,67 @@ pub struct State {
     pub owned_resources: GlobalResources,
     pub current_position: (i32, i32),
     pub direction: Direction,
+    /// Change in stock over the last turn. What did not fit in storage is
+    /// reported as a loss after the change of its good.
     pub delta_production: Vec<Resources>,
+    pub inactive: HashSet<(i32, i32)>,
+    /// What did not fit in storage at the end of the last turn.
+    pub lost_production: Vec<Resources>,
+    pub rubble: HashSet<(i32, i32)>,
+    pub levels: HashMap<(i32, i32), u32>,
+    pub workers: HashMap<(i32, i32), u32>,
+    pub priorities: Vec<Building>,
+    /// Percent of the way to the next person moving in.
+    pub growth_progress: u32,
+    /// City-wide happiness, the average over the houses.
+    pub happiness: i32,
+    pub trade_rates: TradeRates,
+    /// How much each good's price went up with recent purchases.
+    pub demand: HashMap<Good, u32>,
+    /// Turns left with wood production halved by a storm.
+    pub storm_turns: u32,
+    /// Turns left with quarries boosted by a mining boom.
+    pub boom_turns: u32,
+    /// Every event so far, with the turn after which it happened.
+    pub events: Vec<(u32, Event)>,
+    /// Seed of the terrain map, `None` for plains everywhere.
+    pub terrain_seed: Option<u64>,
+    /// Rings of the spiral completed so far.
+    pub rings: u32,
+    /// Buildings that can still be placed without paying their cost.
+    pub free_buildings: u32,
+    pub modifiers: Vec<Modifier>,
+    pub winding: Winding,
+    /// Whether the player may change the winding at corners.
+    pub steering: bool,
+    pub skip_tokens: u32,
+    /// Cells the spiral went past without building, they can be built on
+    /// later like rubble.
+    pub skipped: HashSet<(i32, i32)>,
+    /// Whether buildings go on any empty cell next to the city instead of
+    /// following the spiral.
+    pub frontier: bool,
+    /// Wonders standing, with their cells in the order they were built.
+    pub wonders: Vec<(Building, Vec<(i32, i32)>)>,
+    /// Contracts accepted and not settled yet.
+    pub contracts: Vec<Contract>,
+    /// Contract that can be accepted until the next one is offered.
+    pub contract_offer: Option<Contract>,
+    /// Contracts settled at the end of the last turn, with whether they were
+    /// met.
+    pub settled_contracts: Vec<(Contract, bool)>,
+    /// Next tribute demanded from the city.
+    pub tribute: Tribute,
+    /// Whether `tribute` could not be paid when due, which ends the run.
+    pub tribute_failed: bool,
+    /// Times the offer can still be redrawn.
+    pub rerolls: u32,
+    /// Buildings offered from the start thanks to the player's profile.
+    pub unlocked: Vec<Building>,
+    /// Perks picked before the first turn, they change the rules of the run.
+    pub perks: Vec<Perk>,
+    /// Perks one of which can be picked before the first turn.
+    pub perk_draft: Vec<Perk>,
 }
 
 impl State {
@@ -39,50 +196,126 @@ impl State {
             current_position: (0, 0),
             direction: Direction::Right,
             delta_production: vec![],
+            inactive: HashSet::new(),
+            lost_production: vec![],
+            rubble: HashSet::new(),
+            levels: HashMap::new(),
+            workers: HashMap::new(),
+            priorities: DEFAULT_PRIORITIES.to_vec(),
+            growth_progress: 0,
+            happiness: 0,
+            trade_rates: DEFAULT_TRADE_RATES,
+            demand: HashMap::new(),
+            storm_turns: 0,
+            boom_turns: 0,
+            events: vec![],
+            terrain_seed: None,
+            rings: 0,
+            free_buildings: 0,
+            modifiers: vec![],
+            winding: Winding::Clockwise,
+            steering: false,
+            skip_tokens: 0,
+            skipped: HashSet::new(),
+            frontier: false,
+            wonders: vec![],
+            contracts: vec![],
+            contract_offer: None,
+            settled_contracts: vec![],
+            tribute: Tribute::after(0),
+            tribute_failed: false,
+            rerolls: 0,
+            unlocked: vec![],
+            perks: vec![],
+            perk_draft: vec![],
         }
     }
 
     pub fn spiral_to_string(&self) -> String {
+        self.spiral_with_overlay(None)
+    }
+
+    /// The spiral with each cell replaced by a heat-map square of `overlay`,
+    /// if any. The next position is still shown.
+    pub fn spiral_with_overlay(&self, overlay: Option<Field>) -> String {
+        self.render(|coordinates| match overlay {
+            Some(field) => field.heat_char(field.value_at(self, coordinates)),
+            None => self.cell_char(coordinates),
+        })
+    }
+
+    /// The spiral with buildings belonging to a district replaced by the
+    /// district's symbol, other buildings greyed out.
+    pub fn districts_to_string(&self) -> String {
+        self.render(
+            |coordinates| match districts::district_at(self, coordinates) {
+                Some(district) => district.convert_to_char(),
+                None if self.spiral.contains_key(&coordinates) => '🔲',
+                None => self.cell_char(coordinates),
+            },
+        )
+    }
+
+    fn cell_char(&self, coordinates: (i32, i32)) -> char {
+        match self.spiral.get(&coordinates) {
+            Some(_) if self.inactive.contains(&coordinates) => '💤',
+            // A wonder shows on its first cell, the others are marked as
+            // part of it
+            Some(b) if b.is_wonder() && self.wonder_cells(*b).first() != Some(&coordinates) => '🟪',
+            Some(b) => b.tier_char(self.level(coordinates)),
+            None if self.rubble.contains(&coordinates) => '🧱',
+            None if self.skipped.contains(&coordinates) => '🔳',
+            None => self.terrain(coordinates).convert_to_char(),
+        }
+    }
+
+    /// The spiral around its bounds, with `cell` giving the character of
+    /// every cell but the next position. When the player can steer at the
+    /// coming corner, the cells the spiral would go to next are marked, 🔸
+    /// for the current winding and 🔹 for the other one.
+    fn render(&self, cell: impl Fn((i32, i32)) -> char) -> String {
         let (x_min, x_max) = self.x_bounds;
         let (y_min, y_max) = self.y_bounds;
+        let next_position = if self.frontier {
+            None
+        } else {
+            Some(self.get_next_position())
+        };
+        let frontier_cells = if self.frontier {
+            self.frontier_cells()
+        } else {
+            vec![]
+        };
+        let next_direction = if self.at_corner() {
+            self.direction.turned(self.winding)
+        } else {
+            self.direction
+        };
+        let alternatives = if self.steering && self.at_corner() {
+            let other = self.direction.turned(self.winding.reversed());
+            let corner = self.get_next_position();
+            Some((next_direction.step(corner), other.step(corner)))
+        } else {
+            None
+        };
         let mut spiral_string = String::new();
         for y in ((y_min - 1)..=(y_max + 1)).rev() {
             let mut new_line = String::new();
             for x in (x_min - 1)..=(x_max + 1) {
-                if (x, y) == self.get_next_position() {
-                    if x < self.x_bounds.0 {
-                        // ↑o
-                        // x←
-                        new_line.push('⮤');
-                    } else if x > self.x_bounds.1 {
-                        // →x
-                        // o↓
-                        new_line.push('⮧');
-                    } else if y < self.y_bounds.0 {
-                        // o↓
-                        // ←x
-                        new_line.push('⮠');
-                    } else if y > self.y_bounds.1 {
-                        // x→
-                        // ↑o
-                        new_line.push('⮣');
-                    } else {
-                        match self.direction {
-                            Direction::Down => new_line.push('🡻'),
-                            Direction::Up => new_line.push('🡹'),
-                            Direction::Left => new_line.push('🡸'),
-                            Direction::Right => new_line.push('🡺'),
-                        }
-                    }
+                if Some((x, y)) == next_position {
+                    new_line.push(self.direction.arrow(next_direction));
                     new_line.push(' ');
                     continue;
                 }
-                let building = self.spiral.get(&(x, y));
-                let new_char = match building {
-                    Some(b) => b.convert_to_char(),
-                    None => '⬛',
-                };
-                new_line.push(new_char);
+                if frontier_cells.contains(&(x, y)) && !self.rubble.contains(&(x, y)) {
+                    new_line.push('➕');
+                    continue;
+                }
+                match alternatives {
+                    Some((chosen, _)) if chosen == (x, y) => new_line.push('🔸'),
+                    Some((_, other)) if other == (x, y) => new_line.push('🔹'),
+                    _ => new_line.push(cell((x, y))),
+                }
                 // // Add space between buidings?
                 // if x != x_max + 1 {
                 //     new_line.push(' ');
@@ -98,43 +331,200 @@ impl State {
         println!("\n===========================");
         println!("=== SpiralCity - Turn {} ===", self.turn);
         println!("===========================\n");
+        if self.tribute_failed {
+            println!(
+                "👑 The tribute due at turn {} could not be paid\n",
+                self.tribute.due
+            );
+        } else {
+            println!("👑 Tribute : {}\n", self.tribute.describe(self.turn));
+        }
         if self.turn != 0 {
             println!(
                 "Last turn : {}\n",
                 Resources::delta_to_string(&self.delta_production)
             );
+            if !self.lost_production.is_empty() {
+                println!(
+                    "            {}\n",
+                    Resources::lost_to_string(&self.lost_production)
+                );
+            }
         }
 
         // TODO: move to resources crate
         println!("Resources");
         println!("----------");
+        let resources = &self.owned_resources;
+        println!(
+            "👥 Population : {} / {} (working/total), room for {}",
+            resources.occupied_people, resources.total_people, resources.housing
+        );
+        if resources.hungry_people > 0 {
+            println!("📉 Growth     : hungry people are leaving the city");
+        } else if resources.total_people >= resources.housing {
+            println!("🏠 Growth     : no room left, build or upgrade houses");
+        } else {
+            println!(
+                "📈 Growth     : +{}% / turn, next arrival at {}%",
+                self.growth_rate(),
+                self.growth_progress
+            );
+        }
+        let effect = match self.happiness_bonus() {
+            1 => "production +1",
+            -1 => "production -1",
+            _ => "no effect on production",
+        };
+        println!("😊 Happiness  : {} ({effect})", self.happiness);
+        println!(
+            "🪵 Wood       : {} / {}",
+            resources.wood, resources.wood_cap
+        );
+        println!(
+            "🪨 Rock       : {} / {}",
+            resources.rock, resources.rock_cap
+        );
+        println!(
+            "🍞 Food       : {} / {}",
+            resources.food, resources.food_cap
+        );
+        if self.owned_resources.hungry_people > 0 {
+            println!(
+                "⚠️ {} hungry people cannot work, build farms or fisheries!",
+                self.owned_resources.hungry_people
+            );
+        }
+        let understaffed = self.understaffed_cells().len();
+        if understaffed > 0 {
+            println!("👷 {understaffed} building(s) lack workers and produce less");
+        }
+        let priorities: Vec<String> = self
+            .priorities
+            .iter()
+            .map(|building| building.convert_to_char().to_string())
+            .collect();
+        println!("Staffing order : {}", priorities.join(" > "));
+        if !self.inactive.is_empty() {
+            println!(
+                "💤 {} building(s) inactive, their upkeep could not be paid",
+                self.inactive.len()
+            );
+        }
+        if self.storm_turns > 0 {
+            println!("🌩️ Storm for {} more turn(s)", self.storm_turns);
+        }
+        if self.boom_turns > 0 {
+            println!("⛏️ Mining boom for {} more turn(s)", self.boom_turns);
+        }
+        let next_ring = self.rings as i32 + 1;
         println!(
-            "👥 Population : {} / {} (occupied/total)",
-            self.owned_resources.occupied_people, self.owned_resources.total_people
+            "🌀 Rings      : {} completed, the next one needs every cell out to {:?}",
+            self.rings,
+            (next_ring, next_ring)
         );
-        println!("🪵 Wood       : {}", self.owned_resources.wood);
-        println!("🪨 Rock       : {}", self.owned_resources.rock);
+        if self.free_buildings > 0 {
+            println!("🎁 {} free building(s) left", self.free_buildings);
+        }
+        for modifier in &self.modifiers {
+            println!("{}", modifier.describe());
+        }
+        for perk in &self.perks {
+            println!("{}", perk.describe());
+        }
+        if self.steering {
+            println!(
+                "🧭 Steering   : turning {:?} at corners, {} skip token(s) left",
+                self.winding, self.skip_tokens
+            );
+        } else if self.skip_tokens > 0 {
+            println!("⏭️ {} skip token(s) left", self.skip_tokens);
+        }
+        if self.rerolls > 0 {
+            println!("🎲 {} reroll(s) left", self.rerolls);
+        }
+        for (wonder, cells) in &self.wonders {
+            println!(
+                "{} stands on {} cells 🟪",
+                wonder.building_to_string(),
+                cells.len()
+            );
+        }
+        for (district, cells) in districts::districts(self) {
+            println!(
+                "{} {:?} of {} buildings (+{}%)",
+                district.convert_to_char(),
+                district,
+                cells.len(),
+                districts::bonus(cells.len())
+            );
+        }
+        if !self.contracts.is_empty() || !self.settled_contracts.is_empty() {
+            println!("\nContracts");
+            println!("---------");
+            for (contract, met) in &self.settled_contracts {
+                let outcome = if *met { "✅ Met" } else { "❌ Failed" };
+                println!("{outcome} : {}", contract.goal.describe());
+            }
+            for contract in &self.contracts {
+                println!("📜 {}", contract.describe(self));
+            }
+        }
+        if !self.events.is_empty() {
+            println!("\nEvents");
+            println!("------");
+            for (turn, event) in self.events.iter().rev().take(3) {
+                println!("Turn {turn} : {}", event.describe());
+            }
+        }
         println!("\nCity");
         println!("----");
         println!("{}", self.spiral_to_string());
+        if self.steering && self.at_corner() {
+            println!(
+                "🔸 next cell after the corner | 🔹 next cell if the spiral turns the other way"
+            );
+        }
     }
 
     pub fn get_next_position(&self) -> (i32, i32) {
-        let (cx, cy) = self.current_position;
-        match self.direction {
-            Direction::Right => (cx + 1, cy),
-            Direction::Left => (cx - 1, cy),
-            Direction::Up => (cx, cy + 1),
-            Direction::Down => (cx, cy - 1),
-        }
+        self.direction.step(self.current_position)
     }
 
-    pub fn choose_building(&self) -> Option<Building> {
-        fn correct_proposition(turn: u32, building1: Building, building2: Building) -> bool {
+    /// Whether the next cell is past the bounds of the spiral, where it
+    /// turns.
+    pub fn at_corner(&self) -> bool {
+        let (nx, ny) = self.get_next_position();
+        nx < self.x_bounds.0 || nx > self.x_bounds.1 || ny < self.y_bounds.0 || ny > self.y_bounds.1
+    }
+
+    pub fn propose_buildings<R: Rng>(&self, rng: &mut R) -> Vec<Building> {
+        fn correct_proposition(
+            turn: u32,
+            unlocked: &dyn Fn(Building) -> bool,
+            fits: &dyn Fn(Building) -> bool,
+            buildable: &dyn Fn(Building) -> bool,
+            building1: Building,
+            building2: Building,
+        ) -> bool {
             if building1 == building2 {
                 return false;
             }
 
+            if !unlocked(building1) || !unlocked(building2) {
+                return false;
+            }
+
+            // At least one of them must fit where it would be built
+            if !fits(building1) && !fits(building2) {
+                return false;
+            }
+
+            // The first offer must leave the player something to build
+            if turn == 0 && !buildable(building1) && !buildable(building2) {
+                return false;
+            }
+
             if turn == 0 && (building1 == Building::Workshop || building2 == Building::Workshop) {
                 return false;
             }
@@ -144,22 +534,141 @@ impl State {
 
         let mut building1 = Building::House;
         let mut building2 = Building::House;
-        while !correct_proposition(self.turn, building1, building2) {
-            building1 = Building::rand();
-            building2 = Building::rand();
+        let unlocked = |building| {
+            milestones::is_unlocked(building, self.rings) || self.unlocked.contains(&building)
+        };
+        let fits = |building| self.target_for(building).is_some();
+        let buildable = |building: Building| building.can_be_built(self);
+        while !correct_proposition(
+            self.turn, &unlocked, &fits, &buildable, building1, building2,
+        ) {
+            building1 = Building::random(rng);
+            building2 = Building::random(rng);
+        }
+
+        // Late in the game, a wonder not standing yet sometimes takes the
+        // second place of the offer, as long as something offered still fits
+        let wonders: Vec<Building> = Building::WONDERS
+            .into_iter()
+            .filter(|&wonder| !self.has_wonder(wonder))
+            .collect();
+        if !self.frontier
+            && self.rings >= WONDER_RING
+            && !wonders.is_empty()
+            && rng.gen_range(0..100) < WONDER_CHANCE
+        {
+            let wonder = wonders[rng.gen_range(0..wonders.len())];
+            if fits(building1) || fits(wonder) {
+                building2 = wonder;
+            }
+        }
+
+        // Perks can widen the offer with more buildings
+        let mut offer = vec![building1, building2];
+        while offer.len() < perks::offer_size(self) {
+            let building = Building::random(rng);
+            let too_early = self.turn == 0 && building == Building::Workshop;
+            if !offer.contains(&building) && unlocked(building) && !too_early {
+                offer.push(building);
+            }
+        }
+        offer
+    }
+
+    pub fn choose_building(&self, offer: &[Building]) -> Option<Action> {
+        if self.tribute_failed {
+            println!("You could not pay the tribute, you loose!");
+            return None;
+        }
+        if self.frontier {
+            println!(
+                "Frontier : {} empty cell(s) ➕ next to the city\n",
+                self.frontier_cells().len()
+            );
+        } else {
+            println!(
+                "Next cell : {}\n",
+                self.terrain(self.get_next_position()).terrain_to_string()
+            );
+        }
+        for (i, building) in offer.iter().enumerate() {
+            println!("[{}] {}", i + 1, building.building_to_string());
+            println!("{}", building.characteristics_to_string(1));
+            let cost = perks::cost(self, *building);
+            if cost != building.cost() {
+                println!(
+                    "    With your perks, it costs {}\n",
+                    Resources::cost_to_string(&cost)
+                );
+            }
+        }
+        if self.free_buildings > 0 {
+            println!("🎁 The next building is free, unless it is a wonder\n");
+        }
+        if let Some(contract) = &self.contract_offer {
+            println!("📜 Contract offered : {}\n", contract.describe(self));
+        }
+
+        let upgradable_cells = self.upgradable_cells();
+        if !upgradable_cells.is_empty() {
+            println!("Upgrades available:");
+            for coordinates in &upgradable_cells {
+                let building = self.spiral[coordinates];
+                let level = self.level(*coordinates);
+                println!(
+                    "    {:?} {} → {} for {}",
+                    coordinates,
+                    building.tier_to_string(level),
+                    building.tier_to_string(level + 1),
+                    Resources::cost_to_string(&building.upgrade_cost(level))
+                );
+            }
+            println!();
         }
 
-        println!("[1] {}", building1.building_to_string());
-        println!("{}", building1.characteristics_to_string());
-        println!("[2] {}", building2.building_to_string());
-        println!("{}", building2.characteristics_to_string());
+        let prices: Vec<String> = Good::ALL
+            .iter()
+            .filter_map(|&good| Some(format!("{} {}", good.symbol(), self.trade_price(good)?)))
+            .collect();
+        if !prices.is_empty() {
+            println!("Market prices for 1 unit: {}\n", prices.join(" | "));
+        }
 
-        if !building1.can_be_built(self) && !building2.can_be_built(self) {
+        if offer.iter().all(|building| !building.can_be_built(self))
+            && upgradable_cells.is_empty()
+            && !self.can_trade()
+            && self.skip_tokens == 0
+            && self.rerolls == 0
+        {
             println!("You cannot build any of the buildings, you loose!");
             return None;
         }
 
-        println!("> Choose building 1 or 2 (Q to quit):");
+        println!("> Choose building 1 to {} (Q to quit):", offer.len());
+        println!(
+            "  'D x y' demolishes the building at (x, y) for {} wood 🪵, 'R n x y' builds building n on the rubble 🧱 at (x, y), 'U x y' upgrades the building at (x, y), 'P name' staffs that kind of building first (e.g. 'P quarry')",
+            DEMOLITION_COST
+        );
+        println!(
+            "  'T give get n' buys n of a good at the market 🏪 (e.g. 'T wood rock 2'), 'O pollution', 'O happiness' or 'O districts' shows a map"
+        );
+        if self.steering && self.at_corner() {
+            println!("  'W' makes the spiral turn the other way at this corner");
+        }
+        if self.skip_tokens > 0 {
+            println!("  'S' leaves the next cell empty for later, using a skip token");
+        }
+        if self.contract_offer.is_some() {
+            println!("  'A' accepts the contract offered");
+        }
+        if self.rerolls > 0 {
+            println!("  'N' draws a new offer, using a reroll");
+        }
+        if self.frontier {
+            println!(
+                "  'B n x y' builds building n on the frontier cell (x, y), '1' or '2' on the nearest cell where it fits"
+            );
+        }
 
         let mut buffer = String::new();
         loop {
@@ -167,114 +676,759 @@ impl State {
                 .read_line(&mut buffer)
                 .expect("Expected first user input");
             match buffer.trim() {
-                "1" => {
-                    if !building1.can_be_built(self) {
-                        println!(
-                            "You cannot build {}, choose another building!",
-                            building1.building_to_string()
-                        )
-                    } else {
-                        return Some(building1);
+                "Q" => return None,
+                "q" => return None,
+                command if command.eq_ignore_ascii_case("O districts") => {
+                    println!("{}", self.districts_to_string());
+                    let legend: Vec<String> = District::ALL
+                        .iter()
+                        .map(|district| format!("{} {district:?}", district.convert_to_char()))
+                        .collect();
+                    println!("{}", legend.join(" | "));
+                }
+                command if command.to_ascii_uppercase().starts_with("O ") => {
+                    match Field::from_name(command[2..].trim()) {
+                        Some(field) => {
+                            println!("{}", self.spiral_with_overlay(Some(field)));
+                            println!("{}", field.legend());
+                        }
+                        None => println!(
+                            "Unknown map, use 'O pollution', 'O happiness' or 'O districts'"
+                        ),
                     }
                 }
-                "2" => {
-                    if !building2.can_be_built(self) {
+                command => match Action::parse(command) {
+                    Some(Action::Build { index }) if index < offer.len() => {
+                        if !offer[index].can_be_built(self) {
+                            println!(
+                                "You cannot build {}, choose another building!",
+                                offer[index].building_to_string()
+                            )
+                        } else {
+                            return Some(Action::Build { index });
+                        }
+                    }
+                    Some(Action::Build { .. }) | None => {
                         println!(
-                            "You cannot build {}, choose another building!",
-                            building2.building_to_string()
+                            "Please enter a correct value: '1', '2', 'B n x y', 'D x y', 'R n x y', 'U x y', 'P name', 'T give get n', 'W', 'S', 'A', 'N' or 'Q'"
                         )
-                    } else {
-                        return Some(building2);
                     }
-                }
-                "Q" => return None,
-                "q" => return None,
-                _ => println!("Please enter a correct value: '1', '2' or 'Q'"),
+                    Some(action) => return Some(action),
+                },
             }
             buffer.clear();
         }
     }
 
-    pub fn turn(&self) -> Option<Self> {
-        self.print();
+    /// Demolish the building at `coordinates`, leaving rubble that can be
+    /// built on again. Its workers are freed.
+    pub fn demolish(&self, coordinates: (i32, i32)) -> Result<Self, String> {
+        if !self.spiral.contains_key(&coordinates) {
+            return Err(format!("there is no building at {coordinates:?}"));
+        }
+        if self.owned_resources.wood < DEMOLITION_COST {
+            return Err(format!("demolishing costs {DEMOLITION_COST} wood"));
+        }
+
         let mut new_state = self.clone();
-        new_state.turn += 1;
-        let new_building = self.choose_building()?;
-
-        // Pay cost
-        let cost = new_building.cost();
-        for resource in cost {
-            match resource {
-                Resources::WorkingPeople(n) => {
-                    new_state.owned_resources.occupied_people += n as u32
-                }
-                Resources::Wood(n) => new_state.owned_resources.wood -= n as u32,
-                Resources::Rock(n) => new_state.owned_resources.rock -= n as u32,
+        new_state.owned_resources.wood -= DEMOLITION_COST;
+        new_state.destroy(coordinates);
+        Ok(new_state)
+    }
+
+    /// Turn the building at `coordinates` into rubble, moving its people
+    /// and workers out. A wonder falls as a whole.
+    pub fn destroy(&mut self, coordinates: (i32, i32)) {
+        let cells = match self
+            .wonders
+            .iter()
+            .position(|(_, cells)| cells.contains(&coordinates))
+        {
+            Some(index) => self.wonders.remove(index).1,
+            None => vec![coordinates],
+        };
+        for coordinates in cells {
+            self.spiral.remove(&coordinates);
+            self.inactive.remove(&coordinates);
+            self.rubble.insert(coordinates);
+            self.levels.remove(&coordinates);
+        }
+        self.update_housing();
+        self.happiness = fields::city_happiness(self);
+        self.assign_workers();
+    }
+
+    /// Build on the next spiral cell.
+    pub fn turn(&self, new_building: Building) -> Self {
+        self.build(new_building, None)
+    }
+
+    /// Empty cells next to a building, sorted. In frontier mode, this is
+    /// where buildings can go.
+    pub fn frontier_cells(&self) -> Vec<(i32, i32)> {
+        let mut cells: Vec<(i32, i32)> = self
+            .spiral
+            .keys()
+            .flat_map(|&(x, y)| [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)])
+            .filter(|neighbour| !self.spiral.contains_key(neighbour))
+            .collect();
+        cells.sort();
+        cells.dedup();
+        cells
+    }
+
+    /// The next `n` cells of the spiral, from the next position on.
+    pub fn next_cells(&self, n: usize) -> Vec<(i32, i32)> {
+        let mut state = self.clone();
+        let mut cells = vec![];
+        for _ in 0..n {
+            cells.push(state.get_next_position());
+            state.advance();
+        }
+        cells
+    }
+
+    /// Whether a milestone pays for `building`. Wonders are never free.
+    pub fn is_free(&self, building: Building) -> bool {
+        self.free_buildings > 0 && !building.is_wonder()
+    }
+
+    pub fn has_wonder(&self, wonder: Building) -> bool {
+        self.wonders.iter().any(|&(building, _)| building == wonder)
+    }
+
+    pub fn wonder_cells(&self, wonder: Building) -> Vec<(i32, i32)> {
+        self.wonders
+            .iter()
+            .find(|&&(building, _)| building == wonder)
+            .map(|(_, cells)| cells.clone())
+            .unwrap_or_default()
+    }
+
+    /// Where `building` goes when simply chosen from the offer: the next
+    /// spiral cell, or in frontier mode the frontier cell nearest to the
+    /// last building. `None` if the terrain there does not allow it. A
+    /// wonder starts on the next spiral cell and needs every cell it spans
+    /// to allow it.
+    pub fn target_for(&self, building: Building) -> Option<(i32, i32)> {
+        if building.is_wonder() {
+            let cells = self.next_cells(building.size());
+            let fits = !self.frontier
+                && !self.has_wonder(building)
+                && cells
+                    .iter()
+                    .all(|&coordinates| self.terrain(coordinates).allows(building));
+            return fits.then_some(cells[0]);
+        }
+        if !self.frontier {
+            let next = self.get_next_position();
+            return self.terrain(next).allows(building).then_some(next);
+        }
+        let (cx, cy) = self.current_position;
+        self.frontier_cells()
+            .into_iter()
+            .filter(|&coordinates| self.terrain(coordinates).allows(building))
+            .min_by_key(|&(x, y)| (x - cx).abs() + (y - cy).abs())
+    }
+
+    /// Build on any frontier cell. Only in frontier mode.
+    pub fn build_at(
+        &self,
+        new_building: Building,
+        coordinates: (i32, i32),
+    ) -> Result<Self, String> {
+        if !self.frontier {
+            return Err(String::from(
+                "buildings follow the spiral in this game, they cannot be placed freely",
+            ));
+        }
+        if new_building.is_wonder() {
+            return Err(String::from("wonders can only be built along the spiral"));
+        }
+        if !self.frontier_cells().contains(&coordinates) {
+            return Err(format!(
+                "{coordinates:?} is not an empty cell next to the city"
+            ));
+        }
+        let terrain = self.terrain(coordinates);
+        if !terrain.allows(new_building) {
+            return Err(format!(
+                "{} cannot be built on {terrain:?}",
+                new_building.building_to_string()
+            ));
+        }
+        Ok(self.build(new_building, Some(coordinates)))
+    }
+
+    /// Build on the rubble or skipped cell at `coordinates` instead of the
+    /// next spiral cell.
+    pub fn rebuild(&self, new_building: Building, coordinates: (i32, i32)) -> Result<Self, String> {
+        if new_building.is_wonder() {
+            return Err(format!(
+                "{} needs {} consecutive spiral cells",
+                new_building.building_to_string(),
+                new_building.size()
+            ));
+        }
+        if !self.rubble.contains(&coordinates) && !self.skipped.contains(&coordinates) {
+            return Err(format!(
+                "there is no rubble or skipped cell at {coordinates:?}"
+            ));
+        }
+        let terrain = self.terrain(coordinates);
+        if !terrain.allows(new_building) {
+            return Err(format!(
+                "{} cannot be built on {terrain:?}",
+                new_building.building_to_string()
+            ));
+        }
+        Ok(self.build(new_building, Some(coordinates)))
+    }
+
+    /// Move the cursor to the next spiral cell, turning the way the spiral
+    /// winds when it grows past its bounds.
+    fn advance(&mut self) {
+        let (nx, ny) = self.get_next_position();
+        if self.at_corner() {
+            self.direction = self.direction.turned(self.winding);
+        }
+        self.x_bounds = (self.x_bounds.0.min(nx), self.x_bounds.1.max(nx));
+        self.y_bounds = (self.y_bounds.0.min(ny), self.y_bounds.1.max(ny));
+        self.current_position = (nx, ny);
+    }
+
+    /// Make the spiral turn the other way from the coming corner on. Only
+    /// in games where the player steers. The cells it would have filled
+    /// along the city after the corner are left behind as skipped cells.
+    pub fn steer(&self) -> Result<Self, String> {
+        if !self.steering {
+            return Err(String::from("the spiral cannot be steered in this game"));
+        }
+        if !self.at_corner() {
+            return Err(String::from("the next cell is not a corner of the spiral"));
+        }
+        let mut new_state = self.clone();
+        new_state.winding = self.winding.reversed();
+        for coordinates in self.side_after_corner(self.winding) {
+            new_state.skipped.insert(coordinates);
+        }
+        // Steering back at the same corner gives them back to the spiral
+        for coordinates in self.side_after_corner(new_state.winding) {
+            new_state.skipped.remove(&coordinates);
+        }
+        Ok(new_state)
+    }
+
+    /// Cells along the city that the spiral fills after the coming corner
+    /// when it winds this way, before it grows past its bounds again.
+    fn side_after_corner(&self, winding: Winding) -> Vec<(i32, i32)> {
+        let direction = self.direction.turned(winding);
+        let within_bounds = |(x, y): (i32, i32)| match direction {
+            Direction::Left | Direction::Right => self.x_bounds.0 <= x && x <= self.x_bounds.1,
+            Direction::Up | Direction::Down => self.y_bounds.0 <= y && y <= self.y_bounds.1,
+        };
+        let mut cells = vec![];
+        let mut coordinates = direction.step(self.get_next_position());
+        while within_bounds(coordinates) {
+            cells.push(coordinates);
+            coordinates = direction.step(coordinates);
+        }
+        cells
+    }
+
+    /// Keep the drafted perk at `index` for the rest of the run. This does
+    /// not end the turn, and the other perks of the draft are gone.
+    pub fn pick_perk(&self, index: usize) -> Result<Self, String> {
+        if self.perk_draft.is_empty() {
+            return Err(String::from(
+                "perks can only be picked once, before the first turn",
+            ));
+        }
+        let Some(&perk) = self.perk_draft.get(index) else {
+            return Err(format!("no perk at index {index} in the draft"));
+        };
+        let mut new_state = self.clone();
+        new_state.perk_draft.clear();
+        new_state.perks.push(perk);
+        perk.apply(&mut new_state);
+        Ok(new_state)
+    }
+
+    /// Use up a reroll, the new offer is drawn by the game. This does not
+    /// end the turn.
+    pub fn reroll(&self) -> Result<Self, String> {
+        if self.rerolls == 0 {
+            return Err(String::from("no reroll left"));
+        }
+        let mut new_state = self.clone();
+        new_state.rerolls -= 1;
+        Ok(new_state)
+    }
+
+    /// Leave the next cell empty for later, using a skip token. This does
+    /// not end the turn.
+    pub fn skip(&self) -> Result<Self, String> {
+        if self.skip_tokens == 0 {
+            return Err(String::from("no skip token left"));
+        }
+        let mut new_state = self.clone();
+        new_state.skip_tokens -= 1;
+        new_state.skipped.insert(self.get_next_position());
+        new_state.advance();
+        Ok(new_state)
+    }
+
+    /// Take on the contract offered. This does not end the turn.
+    pub fn accept_contract(&self) -> Result<Self, String> {
+        if self.contracts.len() >= MAX_CONTRACTS {
+            return Err(format!("at most {MAX_CONTRACTS} contracts can run at once"));
+        }
+        let mut new_state = self.clone();
+        let Some(contract) = new_state.contract_offer.take() else {
+            return Err(String::from("no contract is offered"));
+        };
+        new_state.contracts.push(contract);
+        Ok(new_state)
+    }
+
+    /// Take the tribute once it is due and demand the next one, or end the
+    /// run if it cannot be paid.
+    fn collect_tribute(&mut self) {
+        if self.turn < self.tribute.due || self.tribute_failed {
+            return;
+        }
+        if !self.owned_resources.can_afford(&self.tribute.demand) {
+            self.tribute_failed = true;
+            return;
+        }
+        self.owned_resources.pay(&self.tribute.demand);
+        self.tribute = Tribute::after(self.turn);
+    }
+
+    /// Pay the rewards of the contracts met and the penalties of those whose
+    /// deadline passed.
+    fn settle_contracts(&mut self) {
+        self.settled_contracts.clear();
+        let contracts = std::mem::take(&mut self.contracts);
+        for contract in contracts {
+            if contract.is_met(self) {
+                self.owned_resources.receive(&contract.reward);
+                self.settled_contracts.push((contract, true));
+            } else if self.turn > contract.deadline {
+                self.owned_resources.lose(&contract.penalty);
+                self.settled_contracts.push((contract, false));
+            } else {
+                self.contracts.push(contract);
             }
         }
+        let lost = self.owned_resources.apply_storage_caps();
+        self.lost_production.extend(lost);
+    }
+
+    /// Pay for and place a building, on the next spiral cell or on `target`,
+    /// then play the end of the turn.
+    fn build(&self, new_building: Building, target: Option<(i32, i32)>) -> Self {
+        let mut new_state = self.clone();
+        new_state.turn += 1;
+
+        // Pay cost, unless a milestone made this building free
+        if self.is_free(new_building) {
+            new_state.free_buildings -= 1;
+        } else {
+            new_state
+                .owned_resources
+                .pay(&perks::cost(self, new_building));
+        }
 
         // Update map (coordinates, direction, bounds)
-        let new_coordinates = self.get_next_position();
-        new_state.spiral.insert(new_coordinates, new_building);
-        let (nx, ny) = self.get_next_position();
-        if nx < self.x_bounds.0 {
-            // ↑o
-            // x←
-            new_state.x_bounds.0 = nx;
-            new_state.direction = Direction::Up;
-        } else if nx > self.x_bounds.1 {
-            // →x
-            // o↓
-            new_state.x_bounds.1 = nx;
-            new_state.direction = Direction::Down;
-        } else if ny < self.y_bounds.0 {
-            // o↓
-            // ←x
-            new_state.y_bounds.0 = ny;
-            new_state.direction = Direction::Left;
-        } else if ny > self.y_bounds.1 {
-            // x→
-            // ↑o
-            new_state.y_bounds.1 = ny;
-            new_state.direction = Direction::Right;
-        }
-        new_state.current_position = (nx, ny);
-
-        // Apply effects and update resources
-        let mut nb_people = 0;
-        for (&coordinates, &building) in &new_state.spiral {
-            // Each house adds a person
-            if building == Building::House {
-                nb_people += 1;
+        if let Some(coordinates) = target {
+            new_state.rubble.remove(&coordinates);
+            new_state.skipped.remove(&coordinates);
+            new_state.spiral.insert(coordinates, new_building);
+            // Free placement can grow the city past the spiral's bounds
+            let (x, y) = coordinates;
+            new_state.x_bounds = (new_state.x_bounds.0.min(x), new_state.x_bounds.1.max(x));
+            new_state.y_bounds = (new_state.y_bounds.0.min(y), new_state.y_bounds.1.max(y));
+            if self.frontier {
+                new_state.current_position = coordinates;
+            }
+        } else {
+            let cells = self.next_cells(new_building.size());
+            for &coordinates in &cells {
+                new_state.spiral.insert(coordinates, new_building);
+                new_state.advance();
+            }
+            if new_building.is_wonder() {
+                new_state.wonders.push((new_building, cells));
+            }
+        }
+
+        // Closing rings of the spiral gives rewards
+        let rings = milestones::completed_rings(&new_state);
+        for ring in new_state.rings + 1..=rings {
+            for reward in milestones::rewards(ring) {
+                reward.apply(&mut new_state);
+            }
+        }
+        new_state.rings = rings;
+
+        new_state.end_turn(self);
+        new_state
+    }
+
+    /// Room for people in the houses, whoever no longer fits leaves.
+    fn update_housing(&mut self) {
+        let mut housing = 0;
+        for &coordinates in self.spiral.keys() {
+            housing += perks::housing(self, coordinates);
+        }
+        let resources = &mut self.owned_resources;
+        resources.housing = housing;
+        resources.total_people = resources.total_people.min(housing);
+    }
+
+    /// Percent of a new person moving in per turn. People only come when
+    /// there is room and nobody is hungry, faster with food in stock and
+    /// for houses next to a forest.
+    pub fn growth_rate(&self) -> u32 {
+        let resources = &self.owned_resources;
+        if resources.total_people >= resources.housing || resources.hungry_people > 0 {
+            return 0;
+        }
+        let mut rate = 25 + 10 * self.happiness;
+        if resources.food >= resources.total_people {
+            rate += 25;
+        }
+        for (&(x, y), &building) in &self.spiral {
+            if building == Building::House
+                && [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .iter()
+                    .any(|neighbour| self.spiral.get(neighbour) == Some(&Building::Forest))
+            {
+                rate += 10;
+            }
+        }
+        rate.clamp(0, 100) as u32
+    }
+
+    /// Production bonus of every building, +1 in a happy city and -1 in an
+    /// unhappy one.
+    pub fn happiness_bonus(&self) -> i32 {
+        match self.happiness {
+            ..=-2 => -1,
+            2.. => 1,
+            _ => 0,
+        }
+    }
+
+    /// Immigration and emigration: one hungry person leaves each turn,
+    /// otherwise people move in following the growth rate.
+    fn migrate(&mut self) {
+        if self.owned_resources.hungry_people > 0 {
+            self.owned_resources.total_people -= 1;
+            self.growth_progress = 0;
+            return;
+        }
+        self.growth_progress += self.growth_rate();
+        if self.growth_progress >= 100 {
+            self.growth_progress -= 100;
+            self.owned_resources.total_people += 1;
+        }
+    }
+
+    pub fn terrain(&self, coordinates: (i32, i32)) -> Terrain {
+        match self.terrain_seed {
+            Some(seed) => Terrain::generate(seed, coordinates),
+            None => Terrain::Plains,
+        }
+    }
+
+    /// Tier of the best market of the city, if it has one.
+    pub fn market_level(&self) -> Option<u32> {
+        self.spiral
+            .iter()
+            .filter(|&(coordinates, &building)| {
+                building == Building::Market && !self.inactive.contains(coordinates)
+            })
+            .map(|(&coordinates, _)| self.level(coordinates))
+            .max()
+    }
+
+    /// Price of the next unit of `good`, in units of any other good, or
+    /// `None` without a market.
+    pub fn trade_price(&self, good: Good) -> Option<u32> {
+        let base = self.trade_rates.base_price(self.market_level()?);
+        Some(base + self.demand.get(&good).unwrap_or(&0))
+    }
+
+    /// Whether at least one unit of some good can be bought right now.
+    pub fn can_trade(&self) -> bool {
+        Good::ALL.iter().any(|&get| {
+            Good::ALL
+                .iter()
+                .any(|&give| give != get && self.trade(give, get, 1).is_ok())
+        })
+    }
+
+    /// Buy `amount` units of `get`, paying with `give`. Each unit costs more
+    /// than the last. This does not end the turn.
+    pub fn trade(&self, give: Good, get: Good, amount: u32) -> Result<Self, String> {
+        if give == get {
+            return Err(String::from("a good cannot be traded for itself"));
+        }
+        if amount == 0 {
+            return Err(String::from("nothing to trade"));
+        }
+        let Some(mut price) = self.trade_price(get) else {
+            return Err(String::from("trading needs a market 🏪"));
+        };
+        let mut paid = 0;
+        for _ in 0..amount {
+            paid += price;
+            price += self.trade_rates.increase;
+        }
+
+        let mut new_state = self.clone();
+        new_state
+            .owned_resources
+            .exchange(give, paid, get, amount)?;
+        *new_state.demand.entry(get).or_insert(0) += self.trade_rates.increase * amount;
+        Ok(new_state)
+    }
+
+    pub fn workers_on(&self, coordinates: (i32, i32)) -> u32 {
+        *self.workers.get(&coordinates).unwrap_or(&0)
+    }
+
+    /// Active buildings that did not get all the workers they need.
+    pub fn understaffed_cells(&self) -> Vec<(i32, i32)> {
+        let mut cells: Vec<(i32, i32)> = self
+            .spiral
+            .iter()
+            .filter(|&(coordinates, building)| {
+                !self.inactive.contains(coordinates)
+                    && self.workers_on(*coordinates) < building.workers_needed()
+            })
+            .map(|(&coordinates, _)| coordinates)
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    /// Share the people who are fed between the active buildings that need
+    /// workers, following `priorities` and then the coordinates.
+    pub fn assign_workers(&mut self) {
+        self.workers.clear();
+        self.owned_resources.occupied_people = 0;
+        let mut available = self.owned_resources.available_people();
+        let mut cells: Vec<((i32, i32), Building)> = self
+            .spiral
+            .iter()
+            .filter(|&(coordinates, building)| {
+                building.workers_needed() > 0 && !self.inactive.contains(coordinates)
+            })
+            .map(|(&coordinates, &building)| (coordinates, building))
+            .collect();
+        cells.sort_by_key(|&(coordinates, building)| {
+            let rank = self.priorities.iter().position(|&b| b == building);
+            (rank.unwrap_or(self.priorities.len()), coordinates)
+        });
+        for (coordinates, building) in cells {
+            let assigned = building.workers_needed().min(available);
+            if assigned == 0 {
+                break;
+            }
+            self.workers.insert(coordinates, assigned);
+            self.owned_resources.occupied_people += assigned;
+            available -= assigned;
+        }
+    }
+
+    /// Staff this kind of building before any other. This does not end the
+    /// turn, workers are moved right away.
+    pub fn prioritise(&self, building: Building) -> Result<Self, String> {
+        if building.workers_needed() == 0 {
+            return Err(format!(
+                "{} does not need workers",
+                building.building_to_string()
+            ));
+        }
+        let mut new_state = self.clone();
+        new_state.priorities.retain(|&b| b != building);
+        new_state.priorities.insert(0, building);
+        new_state.assign_workers();
+        Ok(new_state)
+    }
+
+    pub fn level(&self, coordinates: (i32, i32)) -> u32 {
+        *self.levels.get(&coordinates).unwrap_or(&1)
+    }
+
+    pub fn check_upgrade(&self, coordinates: (i32, i32)) -> Result<(), String> {
+        let Some(building) = self.spiral.get(&coordinates) else {
+            return Err(format!("there is no building at {coordinates:?}"));
+        };
+        if building.is_wonder() {
+            return Err(format!(
+                "{} cannot be upgraded",
+                building.building_to_string()
+            ));
+        }
+        let level = self.level(coordinates);
+        if level >= MAX_LEVEL {
+            return Err(format!(
+                "{} is already at the highest tier",
+                building.tier_name(level)
+            ));
+        }
+        if !self
+            .owned_resources
+            .can_afford(&building.upgrade_cost(level))
+        {
+            return Err(format!(
+                "upgrading to {} costs {}",
+                building.tier_name(level + 1),
+                Resources::cost_to_string(&building.upgrade_cost(level))
+            ));
+        }
+        Ok(())
+    }
+
+    /// Cells whose building can be upgraded right now.
+    pub fn upgradable_cells(&self) -> Vec<(i32, i32)> {
+        let mut cells: Vec<(i32, i32)> = self
+            .spiral
+            .keys()
+            .copied()
+            .filter(|&coordinates| self.check_upgrade(coordinates).is_ok())
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    /// Upgrade the building at `coordinates` to its next tier. This takes
+    /// the turn instead of building something new.
+    pub fn upgrade(&self, coordinates: (i32, i32)) -> Result<Self, String> {
+        self.check_upgrade(coordinates)?;
+        let building = self.spiral[&coordinates];
+        let level = self.level(coordinates);
+
+        let mut new_state = self.clone();
+        new_state.turn += 1;
+        new_state.owned_resources.pay(&building.upgrade_cost(level));
+        new_state.levels.insert(coordinates, level + 1);
+        new_state.end_turn(self);
+        Ok(new_state)
+    }
+
+    /// Upkeep, production, feeding and storage at the end of every turn.
+    fn end_turn(&mut self, previous: &State) {
+        // The draft is only open before the first turn
+        self.perk_draft.clear();
+
+        // Market prices come back down
+        let recovery = self.trade_rates.recovery;
+        self.demand.retain(|_, demand| {
+            *demand = demand.saturating_sub(recovery);
+            *demand > 0
+        });
+
+        // Pay upkeep, buildings that cannot be maintained stop working
+        self.inactive.clear();
+        let mut coordinates_list: Vec<(i32, i32)> = self.spiral.keys().copied().collect();
+        coordinates_list.sort();
+        for coordinates in coordinates_list {
+            let building = self.spiral[&coordinates];
+            if !self.owned_resources.pay_upkeep(&building.upkeep()) {
+                self.inactive.insert(coordinates);
+            }
+        }
+
+        // Houses give room for people, who move in and out over time, faster
+        // when they are happy
+        self.update_housing();
+        self.happiness = fields::city_happiness(self);
+        self.migrate();
+
+        // Put people to work, then apply effects and update resources
+        self.assign_workers();
+        for (&coordinates, &building) in &self.spiral {
+            if self.inactive.contains(&coordinates) {
                 continue;
             }
 
             // Update other resources
-            let option_produced_resources = building.production(coordinates, &new_state);
+            let option_produced_resources = building.production(coordinates, self);
             if let Some(produced_resources) = option_produced_resources {
                 match produced_resources {
-                    Resources::Wood(n) => new_state.owned_resources.wood += n as u32,
-                    Resources::Rock(n) => new_state.owned_resources.rock += n as u32,
+                    Resources::Wood(n) => self.owned_resources.wood += n as u32,
+                    Resources::Rock(n) => self.owned_resources.rock += n as u32,
+                    Resources::Food(n) => self.owned_resources.food += n as u32,
                     _ => {}
                 }
             }
         }
-        new_state.owned_resources.total_people = nb_people;
 
-        // Update delta_production
-        new_state.delta_production.clear();
-        if new_building == Building::House {
-            new_state.delta_production.push(Resources::WorkingPeople(1));
-        }
-        if new_state.owned_resources.wood != self.owned_resources.wood {
-            let diff = new_state.owned_resources.wood as i32 - self.owned_resources.wood as i32;
-            new_state.delta_production.push(Resources::Wood(diff));
+        // Storms and booms wear off once they have weighed on production
+        self.storm_turns = self.storm_turns.saturating_sub(1);
+        self.boom_turns = self.boom_turns.saturating_sub(1);
+
+        // Feed the population
+        self.owned_resources.feed_people();
+
+        // Update storage capacity and lose what does not fit
+        let mut base = BASE_STORAGE;
+        if self.modifiers.contains(&Modifier::Granaries) {
+            base += 10;
         }
-        if new_state.owned_resources.rock != self.owned_resources.rock {
-            let diff = new_state.owned_resources.rock as i32 - self.owned_resources.rock as i32;
-            new_state.delta_production.push(Resources::Rock(diff));
+        let (mut wood_cap, mut rock_cap, mut food_cap) = (base, base, base);
+        for (&coordinates, &building) in &self.spiral {
+            for capacity in building.storage(coordinates, self) {
+                match capacity {
+                    Resources::Wood(n) => wood_cap += n as u32,
+                    Resources::Rock(n) => rock_cap += n as u32,
+                    Resources::Food(n) => food_cap += n as u32,
+                    Resources::WorkingPeople(_) => {}
+                }
+            }
         }
+        self.owned_resources.wood_cap = wood_cap;
+        self.owned_resources.rock_cap = rock_cap;
+        self.owned_resources.food_cap = food_cap;
+        self.lost_production = self.owned_resources.apply_storage_caps();
 
-        Some(new_state)
+        // Contracts are checked once the turn's production is in
+        self.settle_contracts();
+        self.collect_tribute();
+
+        // Update delta_production
+        self.delta_production.clear();
+        if self.owned_resources.total_people != previous.owned_resources.total_people {
+            let diff = self.owned_resources.total_people as i32
+                - previous.owned_resources.total_people as i32;
+            self.delta_production.push(Resources::WorkingPeople(diff));
+        }
+        // Each good changes by what came in and went out, then loses what did
+        // not fit in storage
+        for good in Good::ALL {
+            let lost: i32 = self
+                .lost_production
+                .iter()
+                .map(|&resource| match (resource, good) {
+                    (Resources::Wood(n), Good::Wood)
+                    | (Resources::Rock(n), Good::Rock)
+                    | (Resources::Food(n), Good::Food) => n,
+                    _ => 0,
+                })
+                .sum();
+            let diff = self.owned_resources.amount(good) as i32 + lost
+                - previous.owned_resources.amount(good) as i32;
+            if diff != 0 {
+                self.delta_production.push(good.resources(diff));
+            }
+            if lost > 0 {
+                self.delta_production.push(good.resources(-lost));
+            }
+        }
     }
 }
@@ -0,0 +1,105 @@
+//! Ground under the spiral. Terrain is a pure function of a seed and the
+//! coordinates, so the whole map exists before the cursor gets there.
+
+use serde::Serialize;
+
+use crate::buildings::Building;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Terrain {
+    Plains,
+    Hills,
+    River,
+    Mountain,
+    Fertile,
+}
+
+impl Terrain {
+    pub const ALL: [Terrain; 5] = [
+        Terrain::Plains,
+        Terrain::Hills,
+        Terrain::River,
+        Terrain::Mountain,
+        Terrain::Fertile,
+    ];
+
+    /// Terrain of a cell for a given map seed. The first house and the
+    /// cells next to it always stand on plains, so that the first building
+    /// does not need a river or a mountain.
+    pub fn generate(seed: u64, coordinates: (i32, i32)) -> Self {
+        let (x, y) = coordinates;
+        if x.abs() + y.abs() <= 1 {
+            return Terrain::Plains;
+        }
+        let mut hash = seed ^ (((x as u32 as u64) << 32) | y as u32 as u64);
+        // splitmix64 finaliser
+        hash = hash.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+        match hash % 100 {
+            0..50 => Terrain::Plains,
+            50..65 => Terrain::Hills,
+            65..75 => Terrain::River,
+            75..85 => Terrain::Mountain,
+            _ => Terrain::Fertile,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|t| t == self).unwrap()
+    }
+
+    pub fn convert_to_char(&self) -> char {
+        match *self {
+            Terrain::Plains => '⬛',
+            Terrain::Hills => '🟫',
+            Terrain::River => '🟦',
+            Terrain::Mountain => '⬜',
+            Terrain::Fertile => '🟩',
+        }
+    }
+
+    pub fn terrain_to_string(&self) -> String {
+        let effect = match *self {
+            Terrain::Plains => "no effect",
+            Terrain::Hills => "quarries +1 rock 🪨",
+            Terrain::River => "only fisheries, +1 food 🍞, forests next to it +1 wood 🪵",
+            Terrain::Mountain => "only quarries, +2 rock 🪨",
+            Terrain::Fertile => "farms +1 food 🍞",
+        };
+        format!("{} {:?} ({effect})", self.convert_to_char(), self)
+    }
+
+    /// Whether `building` can stand on this terrain.
+    pub fn allows(&self, building: Building) -> bool {
+        match *self {
+            Terrain::River => building == Building::Fishery,
+            Terrain::Mountain => building == Building::Quarry,
+            _ => true,
+        }
+    }
+}
+
+#[test]
+fn test_generate_terrain() {
+    for coordinates in [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)] {
+        assert!((0..100).all(|seed| Terrain::generate(seed, coordinates) == Terrain::Plains));
+    }
+    let map: Vec<Terrain> = (-10..10)
+        .flat_map(|x| (-10..10).map(move |y| Terrain::generate(42, (x, y))))
+        .collect();
+    for terrain in Terrain::ALL {
+        assert!(map.contains(&terrain));
+    }
+    assert_eq!(
+        Terrain::generate(42, (3, -7)),
+        Terrain::generate(42, (3, -7))
+    );
+    assert_ne!(
+        map,
+        (-10..10)
+            .flat_map(|x| (-10..10).map(move |y| Terrain::generate(43, (x, y))))
+            .collect::<Vec<Terrain>>()
+    );
+}
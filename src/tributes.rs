@@ -0,0 +1,72 @@
+//! Tributes demanded from the city at regular checkpoints. Each one asks
+//! for more than the last, and a tribute that cannot be paid when it is due
+//! ends the run.
+
+use serde::Serialize;
+
+use crate::resources::{Good, Resources};
+
+/// Turns between two tributes.
+pub const TRIBUTE_INTERVAL: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tribute {
+    /// Turn at the end of which the tribute is taken.
+    pub due: u32,
+    pub demand: Vec<Resources>,
+}
+
+impl Tribute {
+    /// The first tribute due after `turn`: 10 wood and 5 rock at turn 10,
+    /// then 5 more of each every checkpoint.
+    pub fn after(turn: u32) -> Self {
+        let due = (turn / TRIBUTE_INTERVAL + 1) * TRIBUTE_INTERVAL;
+        let checkpoint = (due / TRIBUTE_INTERVAL) as i32;
+        Self {
+            due,
+            demand: vec![
+                Resources::Wood(5 + 5 * checkpoint),
+                Resources::Rock(5 * checkpoint),
+            ],
+        }
+    }
+
+    /// How much of `good` is demanded.
+    pub fn amount(&self, good: Good) -> u32 {
+        self.demand
+            .iter()
+            .map(|&resource| match (resource, good) {
+                (Resources::Wood(n), Good::Wood)
+                | (Resources::Rock(n), Good::Rock)
+                | (Resources::Food(n), Good::Food) => n as u32,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn describe(&self, turn: u32) -> String {
+        format!(
+            "{} due in {} turn(s), at the end of turn {}",
+            Resources::cost_to_string(&self.demand),
+            self.due.saturating_sub(turn),
+            self.due
+        )
+    }
+}
+
+#[test]
+fn test_tributes() {
+    let first = Tribute::after(0);
+    assert_eq!(first.due, 10);
+    assert_eq!(first.demand, vec![Resources::Wood(10), Resources::Rock(5)]);
+    assert_eq!(Tribute::after(9), first);
+    assert_eq!(first.amount(Good::Rock), 5);
+    assert_eq!(first.amount(Good::Food), 0);
+
+    let second = Tribute::after(10);
+    assert_eq!(second.due, 20);
+    assert_eq!(
+        second.demand,
+        vec![Resources::Wood(15), Resources::Rock(10)]
+    );
+}